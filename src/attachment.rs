@@ -0,0 +1,54 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// A file sent along with a message, either as an attachment or as an inline
+/// image. Inline images are referenced by filename, e.g. `<img src="cid:logo.png">`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    /// Creates an attachment from in-memory bytes. The content type is guessed
+    /// from the filename; use [`Attachment::with_content_type`] to override it.
+    pub fn from_bytes(filename: &str, data: impl Into<Vec<u8>>) -> Self {
+        Attachment {
+            filename: filename.to_string(),
+            content_type: mime_guess::from_path(filename)
+                .first_or_octet_stream()
+                .to_string(),
+            data: data.into(),
+        }
+    }
+
+    /// Reads the file at `path`, named after the last component of the path.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+        Ok(Attachment::from_bytes(filename, fs::read(path)?))
+    }
+
+    /// Reads `reader` to the end.
+    pub fn from_reader(filename: &str, mut reader: impl Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Attachment::from_bytes(filename, data))
+    }
+
+    pub fn with_content_type(mut self, content_type: &str) -> Self {
+        self.content_type = content_type.to_string();
+        self
+    }
+
+    pub(crate) fn part(&self) -> reqwest::Result<reqwest::multipart::Part> {
+        reqwest::multipart::Part::bytes(self.data.clone())
+            .file_name(self.filename.clone())
+            .mime_str(&self.content_type)
+    }
+}
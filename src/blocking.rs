@@ -0,0 +1,70 @@
+//! A blocking Mailgun client.
+//!
+//! Each call drives the async [`crate::Mailgun`] on a single-threaded runtime
+//! owned by the client, so it must not be used from within an async context.
+
+use crate::{BatchResponse, EmailAddress, Message, MimeMessage, Region, SendResponse, SendResult};
+use serde_json::Value;
+use std::io;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+#[derive(Clone)]
+pub struct Mailgun {
+    pub(crate) inner: crate::Mailgun,
+    pub(crate) runtime: Arc<Runtime>,
+}
+
+impl Mailgun {
+    /// Fails only if the client's runtime cannot be started.
+    pub fn new(api_key: &str, domain: &str) -> io::Result<Self> {
+        Mailgun::from_async(crate::Mailgun::new(api_key, domain))
+    }
+
+    /// Wraps an already configured async client.
+    pub fn from_async(inner: crate::Mailgun) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Mailgun {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.inner = self.inner.with_region(region);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.inner = self.inner.with_base_url(base_url);
+        self
+    }
+
+    pub fn domain(&self) -> &str {
+        self.inner.domain()
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.inner.base_url()
+    }
+
+    pub fn send(&self, sender: &EmailAddress, message: &Message) -> SendResult<SendResponse> {
+        self.runtime.block_on(self.inner.send(sender, message))
+    }
+
+    pub fn send_batch(
+        &self,
+        sender: &EmailAddress,
+        message: &Message,
+        recipients: &[(EmailAddress, Value)],
+    ) -> SendResult<BatchResponse> {
+        self.runtime
+            .block_on(self.inner.send_batch(sender, message, recipients))
+    }
+
+    pub fn send_mime(&self, message: &MimeMessage) -> SendResult<SendResponse> {
+        self.runtime.block_on(self.inner.send_mime(message))
+    }
+}
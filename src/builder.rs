@@ -0,0 +1,356 @@
+use crate::{Attachment, EmailAddress, Error, Message, SendOptions};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Builds a [`Message`], checking that it can be sent.
+#[derive(Default)]
+pub struct MessageBuilder {
+    message: Message,
+    error: Option<Error>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        MessageBuilder::default()
+    }
+
+    pub fn to(mut self, address: EmailAddress) -> Self {
+        self.message.to.push(address);
+        self
+    }
+
+    pub fn cc(mut self, address: EmailAddress) -> Self {
+        self.message.cc.push(address);
+        self
+    }
+
+    pub fn bcc(mut self, address: EmailAddress) -> Self {
+        self.message.bcc.push(address);
+        self
+    }
+
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.message.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.message.text = Some(text.to_string());
+        self
+    }
+
+    pub fn html(mut self, html: &str) -> Self {
+        self.message.html = Some(html.to_string());
+        self
+    }
+
+    pub fn template(mut self, template: &str) -> Self {
+        self.message.template = Some(template.to_string());
+        self
+    }
+
+    /// Sets a single template variable. Values can be strings, numbers,
+    /// booleans or nested `serde_json::Value`s.
+    pub fn template_var(mut self, name: &str, value: impl Into<Value>) -> Self {
+        let vars = self
+            .message
+            .template_vars
+            .get_or_insert_with(|| Value::Object(Map::new()));
+        if !vars.is_object() {
+            *vars = Value::Object(Map::new());
+        }
+        if let Value::Object(vars) = vars {
+            vars.insert(name.to_string(), value.into());
+        }
+        self
+    }
+
+    /// Replaces the template variables with `vars`, which must serialize to
+    /// a JSON object.
+    pub fn template_vars<T: Serialize>(mut self, vars: &T) -> Self {
+        match serde_json::to_value(vars) {
+            Ok(vars @ Value::Object(_)) => self.message.template_vars = Some(vars),
+            Ok(_) => {
+                self.error = Some(invalid("template variables must be a JSON object"));
+            }
+            Err(err) => {
+                self.error = Some(Error::InvalidMessage(format!(
+                    "template variables could not be serialized: {}",
+                    err
+                )));
+            }
+        }
+        self
+    }
+
+    pub fn template_version(mut self, version: &str) -> Self {
+        self.message.template_version = Some(version.to_string());
+        self
+    }
+
+    /// Generates a text part from the rendered template.
+    pub fn template_text(mut self, template_text: bool) -> Self {
+        self.message.template_text = template_text;
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.message.attachments.push(attachment);
+        self
+    }
+
+    pub fn inline(mut self, image: Attachment) -> Self {
+        self.message.inline.push(image);
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.message
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn user_variable(mut self, name: &str, value: Value) -> Self {
+        self.message.user_variables.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.message.options.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn options(mut self, options: SendOptions) -> Self {
+        self.message.options = options;
+        self
+    }
+
+    /// Returns the message, or [`Error::InvalidMessage`] if it has no
+    /// recipient, no subject, no body, both a body and a template, or
+    /// template variables that could not be serialized.
+    pub fn build(self) -> Result<Message, Error> {
+        if self.message.to.is_empty() {
+            return Err(invalid("a message needs at least one `to` recipient"));
+        }
+        self.validate()
+    }
+
+    /// Like [`MessageBuilder::build`], for a message sent with
+    /// [`Mailgun::send_batch`](crate::Mailgun::send_batch), which supplies
+    /// the recipients. The message must not have `to`, `cc` or `bcc`
+    /// recipients of its own.
+    pub fn build_batch(self) -> Result<Message, Error> {
+        let message = &self.message;
+        if !message.to.is_empty() || !message.cc.is_empty() || !message.bcc.is_empty() {
+            return Err(invalid(
+                "a batch message cannot have to, cc or bcc recipients",
+            ));
+        }
+        self.validate()
+    }
+
+    fn validate(self) -> Result<Message, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut message = self.message;
+        // Empty strings would still be sent, and e.g. override a template's subject.
+        clear_empty(&mut message.subject);
+        clear_empty(&mut message.text);
+        clear_empty(&mut message.html);
+        clear_empty(&mut message.template);
+        let has_body = message.text.is_some() || message.html.is_some();
+        let has_template = message.template.is_some();
+
+        if has_body && has_template {
+            return Err(invalid(
+                "a message can have a text/html body or a template, not both",
+            ));
+        }
+        if !has_body && !has_template {
+            return Err(invalid("a message needs a text/html body or a template"));
+        }
+        if message.subject.is_none() && !has_template {
+            return Err(invalid(
+                "a message needs a subject unless it uses a template",
+            ));
+        }
+        message.options.validate()?;
+
+        Ok(message)
+    }
+}
+
+fn clear_empty(field: &mut Option<String>) {
+    if field.as_deref() == Some("") {
+        *field = None;
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidMessage(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::Params;
+    use serde_json::json;
+    use std::time::{Duration, SystemTime};
+
+    fn base() -> MessageBuilder {
+        Message::builder().to(EmailAddress::address("alice@example.com"))
+    }
+
+    fn rejection(result: Result<Message, Error>) -> String {
+        match result {
+            Err(Error::InvalidMessage(reason)) => reason,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("message was built"),
+        }
+    }
+
+    #[test]
+    fn builds_a_message_with_a_body() {
+        let message = base()
+            .subject("Hello")
+            .text("Hi")
+            .html("<p>Hi</p>")
+            .build()
+            .unwrap();
+        assert_eq!(message.subject.as_deref(), Some("Hello"));
+        assert_eq!(message.text.as_deref(), Some("Hi"));
+        assert_eq!(message.html.as_deref(), Some("<p>Hi</p>"));
+        assert!(message.template.is_none());
+    }
+
+    #[test]
+    fn builds_a_message_with_a_template_and_no_subject() {
+        let message = base()
+            .template("welcome")
+            .template_var("name", "Alice")
+            .template_var("count", 3)
+            .build()
+            .unwrap();
+        assert_eq!(message.template.as_deref(), Some("welcome"));
+        assert!(message.subject.is_none());
+        assert_eq!(
+            message.template_vars,
+            Some(json!({ "name": "Alice", "count": 3 }))
+        );
+    }
+
+    #[test]
+    fn drops_empty_strings() {
+        let message = base()
+            .template("welcome")
+            .subject("")
+            .text("")
+            .html("")
+            .build()
+            .unwrap();
+        assert!(message.subject.is_none());
+        assert!(message.text.is_none());
+        assert!(message.html.is_none());
+
+        let mut params = Params::new();
+        message.add_params(&mut params);
+        assert_eq!(params.fields(), vec![("template", "welcome")]);
+    }
+
+    #[test]
+    fn treats_empty_strings_as_missing() {
+        let reason = rejection(base().subject("").text("Hi").build());
+        assert!(reason.contains("subject"), "{}", reason);
+        let reason = rejection(base().subject("Hello").text("").build());
+        assert!(reason.contains("body or a template"), "{}", reason);
+        let reason = rejection(base().subject("Hello").template("").build());
+        assert!(reason.contains("body or a template"), "{}", reason);
+    }
+
+    #[test]
+    fn requires_a_recipient() {
+        let reason = rejection(Message::builder().subject("Hello").text("Hi").build());
+        assert!(reason.contains("recipient"), "{}", reason);
+    }
+
+    #[test]
+    fn rejects_a_body_and_a_template() {
+        let reason = rejection(
+            base()
+                .subject("Hello")
+                .html("<p>Hi</p>")
+                .template("welcome")
+                .build(),
+        );
+        assert!(reason.contains("not both"), "{}", reason);
+    }
+
+    #[test]
+    fn requires_a_body_or_a_template() {
+        let reason = rejection(base().subject("Hello").build());
+        assert!(reason.contains("body or a template"), "{}", reason);
+    }
+
+    #[test]
+    fn requires_a_subject_without_a_template() {
+        let reason = rejection(base().text("Hi").build());
+        assert!(reason.contains("subject"), "{}", reason);
+    }
+
+    #[test]
+    fn requires_template_vars_to_be_an_object() {
+        let reason = rejection(
+            base()
+                .template("welcome")
+                .template_vars(&vec![1, 2])
+                .build(),
+        );
+        assert!(reason.contains("JSON object"), "{}", reason);
+
+        #[derive(Serialize)]
+        struct Vars {
+            name: &'static str,
+        }
+        let message = base()
+            .template("welcome")
+            .template_vars(&Vars { name: "Alice" })
+            .build()
+            .unwrap();
+        assert_eq!(message.template_vars, Some(json!({ "name": "Alice" })));
+    }
+
+    #[test]
+    fn validates_options() {
+        let options = SendOptions {
+            delivery_time: Some(SystemTime::now() + Duration::from_secs(4 * 24 * 3600)),
+            ..Default::default()
+        };
+        let reason = rejection(base().subject("Hello").text("Hi").options(options).build());
+        assert!(reason.contains("3 days"), "{}", reason);
+    }
+
+    #[test]
+    fn builds_batch_messages_without_recipients() {
+        let message = Message::builder()
+            .subject("Hello %recipient.first%")
+            .text("Hi")
+            .build_batch()
+            .unwrap();
+        assert!(message.to.is_empty());
+
+        let reason = rejection(base().subject("Hello").text("Hi").build_batch());
+        assert!(reason.contains("batch"), "{}", reason);
+        let reason = rejection(
+            Message::builder()
+                .bcc(EmailAddress::address("audit@example.com"))
+                .subject("Hello")
+                .text("Hi")
+                .build_batch(),
+        );
+        assert!(reason.contains("batch"), "{}", reason);
+        let reason = rejection(Message::builder().text("Hi").build_batch());
+        assert!(reason.contains("subject"), "{}", reason);
+    }
+}
@@ -0,0 +1,522 @@
+//! The Domains API: sending domains, their DNS records and their tracking,
+//! connection and DKIM settings.
+
+use crate::options::yes_no;
+use crate::params::Params;
+use crate::{Mailgun, SendResult, TrackingClicks};
+use reqwest::Method;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Domain {
+    pub name: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub smtp_login: Option<String>,
+    #[serde(default)]
+    pub spam_action: Option<SpamAction>,
+    #[serde(default)]
+    pub wildcard: bool,
+    #[serde(default)]
+    pub is_disabled: bool,
+    #[serde(default)]
+    pub require_tls: bool,
+    #[serde(default)]
+    pub skip_verification: bool,
+    #[serde(default)]
+    pub web_prefix: Option<String>,
+    #[serde(default)]
+    pub web_scheme: Option<String>,
+}
+
+/// What Mailgun does with messages it considers spam.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpamAction {
+    Disabled,
+    Tag,
+    Block,
+}
+
+impl fmt::Display for SpamAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpamAction::Disabled => write!(f, "disabled"),
+            SpamAction::Tag => write!(f, "tag"),
+            SpamAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+/// A DNS record the domain needs, and whether Mailgun found it.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DnsRecord {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub record_type: String,
+    pub value: String,
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// `valid`, `invalid` or `unknown`.
+    #[serde(default)]
+    pub valid: Option<String>,
+    #[serde(default)]
+    pub is_active: bool,
+    /// The values Mailgun currently sees in DNS.
+    #[serde(default)]
+    pub cached: Vec<String>,
+}
+
+/// A domain together with the DNS records for sending and receiving mail.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DomainDetails {
+    pub domain: Domain,
+    #[serde(default)]
+    pub receiving_dns_records: Vec<DnsRecord>,
+    #[serde(default)]
+    pub sending_dns_records: Vec<DnsRecord>,
+}
+
+/// One page of domains. `total_count` counts every domain on the account.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DomainList {
+    pub total_count: usize,
+    pub items: Vec<Domain>,
+}
+
+/// A domain to create. Unset options use Mailgun's defaults.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NewDomain {
+    pub name: String,
+    pub spam_action: Option<SpamAction>,
+    /// 1024 or 2048.
+    pub dkim_key_size: Option<u32>,
+    pub smtp_password: Option<String>,
+    /// Accept mail for subdomains too.
+    pub wildcard: Option<bool>,
+    pub force_dkim_authority: Option<bool>,
+    /// `http` or `https`, used for tracking links.
+    pub web_scheme: Option<String>,
+}
+
+/// A domain's open, click and unsubscribe tracking settings.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TrackingSettings {
+    pub open: OpenTracking,
+    pub click: ClickTracking,
+    pub unsubscribe: UnsubscribeTracking,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenTracking {
+    pub active: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClickTracking {
+    #[serde(deserialize_with = "click_active")]
+    pub active: TrackingClicks,
+}
+
+/// Unsubscribe tracking, with the footers Mailgun appends to each message.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct UnsubscribeTracking {
+    pub active: bool,
+    #[serde(default)]
+    pub html_footer: Option<String>,
+    #[serde(default)]
+    pub text_footer: Option<String>,
+}
+
+/// How a domain delivers over TLS.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    pub require_tls: bool,
+    pub skip_verification: bool,
+}
+
+/// The result of changing a domain's DKIM authority.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DkimAuthority {
+    pub changed: bool,
+    #[serde(default)]
+    pub sending_dns_records: Vec<DnsRecord>,
+}
+
+#[derive(Deserialize)]
+struct TrackingResponse {
+    tracking: TrackingSettings,
+}
+
+#[derive(Deserialize)]
+struct ConnectionResponse {
+    connection: ConnectionSettings,
+}
+
+/// Click tracking is reported as `true`, `false` or `"htmlonly"`.
+fn click_active<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TrackingClicks, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Active {
+        Bool(bool),
+        Str(String),
+    }
+
+    Ok(match Active::deserialize(deserializer)? {
+        Active::Bool(true) => TrackingClicks::Yes,
+        Active::Bool(false) => TrackingClicks::No,
+        Active::Str(value) => match value.as_str() {
+            "htmlonly" => TrackingClicks::HtmlOnly,
+            "yes" | "true" => TrackingClicks::Yes,
+            "no" | "false" => TrackingClicks::No,
+            other => {
+                return Err(serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Str(other),
+                    &"true, false or \"htmlonly\"",
+                ))
+            }
+        },
+    })
+}
+
+impl Mailgun {
+    /// Lists domains on the account, `limit` at a time starting at `skip`.
+    pub async fn list_domains(
+        &self,
+        limit: Option<usize>,
+        skip: Option<usize>,
+    ) -> SendResult<DomainList> {
+        let mut params = Params::new();
+        params.push_opt("limit", limit);
+        params.push_opt("skip", skip);
+        self.execute(self.request(Method::GET, "v4/domains").query(&params))
+            .await
+    }
+
+    pub async fn create_domain(&self, domain: &NewDomain) -> SendResult<DomainDetails> {
+        let mut params = Params::new();
+        params.push("name", domain.name.as_str());
+        params.push_opt("spam_action", domain.spam_action);
+        params.push_opt("dkim_key_size", domain.dkim_key_size);
+        params.push_opt("smtp_password", domain.smtp_password.as_ref());
+        params.push_opt("wildcard", domain.wildcard);
+        params.push_opt("force_dkim_authority", domain.force_dkim_authority);
+        params.push_opt("web_scheme", domain.web_scheme.as_ref());
+
+        let request = self
+            .request(Method::POST, "v4/domains")
+            .multipart(params.into_form());
+        self.execute(request).await
+    }
+
+    /// Fetches a domain and the DNS records it needs.
+    pub async fn get_domain(&self, name: &str) -> SendResult<DomainDetails> {
+        let path = format!("v4/domains/{}", name);
+        self.execute(self.request(Method::GET, &path)).await
+    }
+
+    /// Asks Mailgun to check the domain's DNS records again.
+    pub async fn verify_domain(&self, name: &str) -> SendResult<DomainDetails> {
+        let path = format!("v4/domains/{}/verify", name);
+        self.execute(self.request(Method::PUT, &path)).await
+    }
+
+    pub async fn delete_domain(&self, name: &str) -> SendResult<()> {
+        let path = format!("v3/domains/{}", name);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+
+    pub async fn get_tracking(&self, domain: &str) -> SendResult<TrackingSettings> {
+        let path = format!("v3/domains/{}/tracking", domain);
+        let response: TrackingResponse = self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.tracking)
+    }
+
+    pub async fn update_open_tracking(&self, domain: &str, active: bool) -> SendResult<()> {
+        let mut params = Params::new();
+        params.push("active", yes_no(active));
+        self.update_tracking(domain, "open", params).await
+    }
+
+    pub async fn update_click_tracking(
+        &self,
+        domain: &str,
+        active: TrackingClicks,
+    ) -> SendResult<()> {
+        let mut params = Params::new();
+        params.push("active", active.to_string());
+        self.update_tracking(domain, "click", params).await
+    }
+
+    /// Turns unsubscribe tracking on or off. Footers that are `None` are left unchanged.
+    pub async fn update_unsubscribe_tracking(
+        &self,
+        domain: &str,
+        settings: &UnsubscribeTracking,
+    ) -> SendResult<()> {
+        let mut params = Params::new();
+        params.push("active", yes_no(settings.active));
+        params.push_opt("html_footer", settings.html_footer.as_ref());
+        params.push_opt("text_footer", settings.text_footer.as_ref());
+        self.update_tracking(domain, "unsubscribe", params).await
+    }
+
+    async fn update_tracking(&self, domain: &str, kind: &str, params: Params) -> SendResult<()> {
+        let path = format!("v3/domains/{}/tracking/{}", domain, kind);
+        self.execute_empty(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+
+    pub async fn get_connection(&self, domain: &str) -> SendResult<ConnectionSettings> {
+        let path = format!("v3/domains/{}/connection", domain);
+        let response: ConnectionResponse = self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.connection)
+    }
+
+    pub async fn update_connection(
+        &self,
+        domain: &str,
+        settings: &ConnectionSettings,
+    ) -> SendResult<()> {
+        let path = format!("v3/domains/{}/connection", domain);
+        let mut params = Params::new();
+        params.push("require_tls", settings.require_tls.to_string());
+        params.push("skip_verification", settings.skip_verification.to_string());
+        self.execute_empty(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+
+    /// Makes the domain sign with its own DKIM key (`true`) or its parent
+    /// domain's (`false`). The returned records show what DNS needs.
+    pub async fn update_dkim_authority(
+        &self,
+        domain: &str,
+        self_authority: bool,
+    ) -> SendResult<DkimAuthority> {
+        let path = format!("v3/domains/{}/dkim_authority", domain);
+        let mut params = Params::new();
+        params.push("self", self_authority.to_string());
+        self.execute(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+
+    pub async fn update_dkim_selector(&self, domain: &str, selector: &str) -> SendResult<()> {
+        let path = format!("v3/domains/{}/dkim_selector", domain);
+        let mut params = Params::new();
+        params.push("dkim_selector", selector);
+        self.execute_empty(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn list_domains(
+        &self,
+        limit: Option<usize>,
+        skip: Option<usize>,
+    ) -> SendResult<DomainList> {
+        self.runtime.block_on(self.inner.list_domains(limit, skip))
+    }
+
+    pub fn create_domain(&self, domain: &NewDomain) -> SendResult<DomainDetails> {
+        self.runtime.block_on(self.inner.create_domain(domain))
+    }
+
+    pub fn get_domain(&self, name: &str) -> SendResult<DomainDetails> {
+        self.runtime.block_on(self.inner.get_domain(name))
+    }
+
+    pub fn verify_domain(&self, name: &str) -> SendResult<DomainDetails> {
+        self.runtime.block_on(self.inner.verify_domain(name))
+    }
+
+    pub fn delete_domain(&self, name: &str) -> SendResult<()> {
+        self.runtime.block_on(self.inner.delete_domain(name))
+    }
+
+    pub fn get_tracking(&self, domain: &str) -> SendResult<TrackingSettings> {
+        self.runtime.block_on(self.inner.get_tracking(domain))
+    }
+
+    pub fn update_open_tracking(&self, domain: &str, active: bool) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_open_tracking(domain, active))
+    }
+
+    pub fn update_click_tracking(&self, domain: &str, active: TrackingClicks) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_click_tracking(domain, active))
+    }
+
+    pub fn update_unsubscribe_tracking(
+        &self,
+        domain: &str,
+        settings: &UnsubscribeTracking,
+    ) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_unsubscribe_tracking(domain, settings))
+    }
+
+    pub fn get_connection(&self, domain: &str) -> SendResult<ConnectionSettings> {
+        self.runtime.block_on(self.inner.get_connection(domain))
+    }
+
+    pub fn update_connection(&self, domain: &str, settings: &ConnectionSettings) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_connection(domain, settings))
+    }
+
+    pub fn update_dkim_authority(
+        &self,
+        domain: &str,
+        self_authority: bool,
+    ) -> SendResult<DkimAuthority> {
+        self.runtime
+            .block_on(self.inner.update_dkim_authority(domain, self_authority))
+    }
+
+    pub fn update_dkim_selector(&self, domain: &str, selector: &str) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_dkim_selector(domain, selector))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn click(active: &str) -> Result<TrackingClicks, serde_json::Error> {
+        let body = format!(r#"{{ "active": {} }}"#, active);
+        serde_json::from_str::<ClickTracking>(&body).map(|click| click.active)
+    }
+
+    #[test]
+    fn parses_click_tracking_in_every_shape() {
+        assert_eq!(click("true").unwrap(), TrackingClicks::Yes);
+        assert_eq!(click("false").unwrap(), TrackingClicks::No);
+        assert_eq!(click(r#""htmlonly""#).unwrap(), TrackingClicks::HtmlOnly);
+        assert_eq!(click(r#""yes""#).unwrap(), TrackingClicks::Yes);
+        assert_eq!(click(r#""no""#).unwrap(), TrackingClicks::No);
+    }
+
+    #[test]
+    fn rejects_unknown_click_tracking() {
+        assert!(click(r#""sometimes""#).is_err());
+        assert!(click(r#""""#).is_err());
+        assert!(click("1").is_err());
+        assert!(click("null").is_err());
+    }
+
+    #[test]
+    fn parses_tracking_settings() {
+        let body = r#"{
+            "tracking": {
+                "click": { "active": "htmlonly" },
+                "open": { "active": false },
+                "unsubscribe": {
+                    "active": true,
+                    "html_footer": "\n<br>\n<p><a href=\"%unsubscribe_url%\">unsubscribe</a></p>\n",
+                    "text_footer": "\n\nTo unsubscribe click: <%unsubscribe_url%>\n\n"
+                }
+            }
+        }"#;
+        let tracking = serde_json::from_str::<TrackingResponse>(body)
+            .unwrap()
+            .tracking;
+        assert_eq!(tracking.click.active, TrackingClicks::HtmlOnly);
+        assert!(!tracking.open.active);
+        assert!(tracking.unsubscribe.active);
+        assert!(tracking
+            .unsubscribe
+            .text_footer
+            .unwrap()
+            .contains("%unsubscribe_url%"));
+    }
+
+    #[test]
+    fn parses_connection_settings() {
+        let body = r#"{ "connection": { "require_tls": true, "skip_verification": false } }"#;
+        let connection = serde_json::from_str::<ConnectionResponse>(body)
+            .unwrap()
+            .connection;
+        assert_eq!(
+            connection,
+            ConnectionSettings {
+                require_tls: true,
+                skip_verification: false,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_domain_details() {
+        let body = r#"{
+            "domain": {
+                "created_at": "Wed, 10 Jul 2013 19:26:52 GMT",
+                "id": "64f1e5d2a0e2c0a2d9c3b1a0",
+                "is_disabled": false,
+                "name": "example.com",
+                "require_tls": false,
+                "skip_verification": false,
+                "smtp_login": "postmaster@example.com",
+                "spam_action": "disabled",
+                "state": "unverified",
+                "type": "custom",
+                "web_prefix": "email",
+                "web_scheme": "https",
+                "wildcard": false
+            },
+            "receiving_dns_records": [
+                {
+                    "cached": [],
+                    "is_active": true,
+                    "priority": "10",
+                    "record_type": "MX",
+                    "valid": "unknown",
+                    "value": "mxa.mailgun.org"
+                }
+            ],
+            "sending_dns_records": [
+                {
+                    "cached": [],
+                    "is_active": true,
+                    "name": "example.com",
+                    "record_type": "TXT",
+                    "valid": "unknown",
+                    "value": "v=spf1 include:mailgun.org ~all"
+                }
+            ]
+        }"#;
+        let details: DomainDetails = serde_json::from_str(body).unwrap();
+        assert_eq!(details.domain.name, "example.com");
+        assert_eq!(details.domain.kind.as_deref(), Some("custom"));
+        assert_eq!(details.domain.spam_action, Some(SpamAction::Disabled));
+        assert_eq!(
+            details.receiving_dns_records[0].priority.as_deref(),
+            Some("10")
+        );
+        assert_eq!(details.sending_dns_records[0].record_type, "TXT");
+    }
+}
@@ -0,0 +1,104 @@
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Errors returned by the Mailgun client.
+#[derive(Debug)]
+pub enum Error {
+    /// 400: the request was rejected, usually because of a missing or invalid parameter.
+    BadRequest { message: String },
+    /// 401: the API key is wrong or not allowed to use this domain.
+    Unauthorized,
+    /// 402: the request parameters were valid but the request failed.
+    RequestFailed { message: String },
+    /// 404: the domain or resource does not exist.
+    NotFound { message: String },
+    /// 429: too many requests; `retry_after` is taken from the `Retry-After` header.
+    RateLimited { retry_after: Option<Duration> },
+    /// 5xx: something went wrong on Mailgun's end.
+    Server { status: StatusCode, body: String },
+    /// Any other unsuccessful status.
+    Unexpected { status: StatusCode, body: String },
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+    /// The message was rejected locally before it was sent.
+    InvalidMessage(String),
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+}
+
+impl Error {
+    /// Converts an unsuccessful response into the matching variant.
+    pub(crate) async fn from_response(res: Response) -> Error {
+        let status = res.status();
+        let retry_after = res
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        let body = match res.text().await {
+            Ok(body) => body,
+            Err(err) => return Error::Transport(err),
+        };
+        let message = || match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(parsed) => parsed.message,
+            Err(_) => body.clone(),
+        };
+
+        match status {
+            StatusCode::BAD_REQUEST => Error::BadRequest { message: message() },
+            StatusCode::UNAUTHORIZED => Error::Unauthorized,
+            StatusCode::PAYMENT_REQUIRED => Error::RequestFailed { message: message() },
+            StatusCode::NOT_FOUND => Error::NotFound { message: message() },
+            StatusCode::TOO_MANY_REQUESTS => Error::RateLimited { retry_after },
+            status if status.is_server_error() => Error::Server { status, body },
+            status => Error::Unexpected { status, body },
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::BadRequest { message } => write!(f, "bad request: {}", message),
+            Error::Unauthorized => write!(f, "unauthorized: check the API key"),
+            Error::RequestFailed { message } => write!(f, "request failed: {}", message),
+            Error::NotFound { message } => write!(f, "not found: {}", message),
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "rate limited: retry after {} seconds",
+                retry_after.as_secs()
+            ),
+            Error::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            Error::Server { status, body } => write!(f, "server error {}: {}", status, body),
+            Error::Unexpected { status, body } => {
+                write!(f, "unexpected status {}: {}", status, body)
+            }
+            Error::Transport(err) => write!(f, "transport error: {}", err),
+            Error::InvalidMessage(reason) => write!(f, "invalid message: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Transport(err)
+    }
+}
@@ -0,0 +1,689 @@
+//! The Events API: what happened to each message, page by page. The same
+//! event types are sent to webhooks, see [`crate::webhooks::WebhookPayload`].
+
+use crate::params::Params;
+use crate::{Mailgun, Page, SendResult};
+use futures_util::stream::{self, Stream};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::SystemTime;
+
+/// An event in a message's life, tagged by its `event` field.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event {
+    Accepted(Delivery),
+    Rejected(Rejection),
+    Delivered(Delivery),
+    Failed(Failure),
+    Opened(Engagement),
+    Clicked(Engagement),
+    Unsubscribed(Engagement),
+    Complained(Engagement),
+    Stored(Stored),
+    /// An event type this crate does not know about yet.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Event {
+    /// The fields every event has, or `None` for an unknown event type.
+    pub fn info(&self) -> Option<&EventInfo> {
+        match self {
+            Event::Accepted(event) | Event::Delivered(event) => Some(&event.info),
+            Event::Rejected(event) => Some(&event.info),
+            Event::Failed(event) => Some(&event.info),
+            Event::Opened(event)
+            | Event::Clicked(event)
+            | Event::Unsubscribed(event)
+            | Event::Complained(event) => Some(&event.info),
+            Event::Stored(event) => Some(&event.info),
+            Event::Unknown => None,
+        }
+    }
+}
+
+/// Fields shared by every event.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EventInfo {
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64,
+    #[serde(default, rename = "log-level")]
+    pub log_level: Option<String>,
+    #[serde(default)]
+    pub recipient: Option<String>,
+    #[serde(default, rename = "recipient-domain")]
+    pub recipient_domain: Option<String>,
+    #[serde(default)]
+    pub message: Option<EventMessage>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The message's `v:` variables.
+    #[serde(default, rename = "user-variables")]
+    pub user_variables: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EventMessage {
+    #[serde(default)]
+    pub headers: MessageHeaders,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub attachments: Vec<EventAttachment>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MessageHeaders {
+    #[serde(default, rename = "message-id")]
+    pub message_id: Option<String>,
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct EventAttachment {
+    pub filename: String,
+    #[serde(default, rename = "content-type")]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// An accepted or delivered message.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Delivery {
+    #[serde(flatten)]
+    pub info: EventInfo,
+    #[serde(default)]
+    pub envelope: Option<Envelope>,
+    #[serde(default, rename = "delivery-status")]
+    pub delivery_status: Option<DeliveryStatus>,
+}
+
+/// A message Mailgun refused to accept.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Rejection {
+    #[serde(flatten)]
+    pub info: EventInfo,
+    #[serde(default)]
+    pub reject: Option<RejectReason>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RejectReason {
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A failed delivery. Temporary failures are retried, permanent ones are not.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Failure {
+    #[serde(flatten)]
+    pub info: EventInfo,
+    pub severity: Severity,
+    /// Why delivery failed, e.g. `bounce`, `suppress-bounce` or `generic`.
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub envelope: Option<Envelope>,
+    #[serde(default, rename = "delivery-status")]
+    pub delivery_status: Option<DeliveryStatus>,
+}
+
+impl Failure {
+    /// Whether Mailgun has given up on this recipient.
+    pub fn is_permanent(&self) -> bool {
+        self.severity == Severity::Permanent
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Permanent,
+    Temporary,
+}
+
+/// A recipient opening, clicking, unsubscribing or complaining.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Engagement {
+    #[serde(flatten)]
+    pub info: EventInfo,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub geolocation: Option<Geolocation>,
+    #[serde(default, rename = "client-info")]
+    pub client_info: Option<ClientInfo>,
+    /// The clicked link, for click events.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// An incoming message stored by a route.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Stored {
+    #[serde(flatten)]
+    pub info: EventInfo,
+    #[serde(default)]
+    pub storage: Option<Storage>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope {
+    #[serde(default)]
+    pub sender: Option<String>,
+    #[serde(default)]
+    pub targets: Option<String>,
+    #[serde(default)]
+    pub transport: Option<String>,
+    #[serde(default, rename = "sending-ip")]
+    pub sending_ip: Option<String>,
+}
+
+/// The receiving server's answer to a delivery attempt.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct DeliveryStatus {
+    #[serde(default)]
+    pub code: Option<u16>,
+    /// The RFC 3463 status code, e.g. `5.1.1`.
+    #[serde(default, rename = "enhanced-code")]
+    pub enhanced_code: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default, rename = "attempt-no")]
+    pub attempt_no: Option<u32>,
+    #[serde(default, rename = "mx-host")]
+    pub mx_host: Option<String>,
+    #[serde(default)]
+    pub tls: Option<bool>,
+    #[serde(default, rename = "certificate-verified")]
+    pub certificate_verified: Option<bool>,
+    #[serde(default, rename = "session-seconds")]
+    pub session_seconds: Option<f64>,
+    #[serde(default, rename = "retry-seconds")]
+    pub retry_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Geolocation {
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientInfo {
+    #[serde(default, rename = "client-type")]
+    pub client_type: Option<String>,
+    #[serde(default, rename = "client-os")]
+    pub client_os: Option<String>,
+    #[serde(default, rename = "client-name")]
+    pub client_name: Option<String>,
+    #[serde(default, rename = "device-type")]
+    pub device_type: Option<String>,
+    #[serde(default, rename = "user-agent")]
+    pub user_agent: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Storage {
+    pub url: String,
+    pub key: String,
+}
+
+/// Event types, for filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Accepted,
+    Rejected,
+    Delivered,
+    Failed,
+    Opened,
+    Clicked,
+    Unsubscribed,
+    Complained,
+    Stored,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            EventKind::Accepted => "accepted",
+            EventKind::Rejected => "rejected",
+            EventKind::Delivered => "delivered",
+            EventKind::Failed => "failed",
+            EventKind::Opened => "opened",
+            EventKind::Clicked => "clicked",
+            EventKind::Unsubscribed => "unsubscribed",
+            EventKind::Complained => "complained",
+            EventKind::Stored => "stored",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Filters for [`Mailgun::get_events`]. Unset filters match everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventQuery {
+    /// Match any of these event types.
+    pub events: Vec<EventKind>,
+    pub recipient: Option<String>,
+    pub message_id: Option<String>,
+    /// Match any of these tags.
+    pub tags: Vec<String>,
+    pub begin: Option<SystemTime>,
+    pub end: Option<SystemTime>,
+    /// Oldest events first. Mailgun defaults to newest first unless `begin` is after `end`.
+    pub ascending: Option<bool>,
+    /// Events per page, at most 300.
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        if !self.events.is_empty() {
+            let events: Vec<String> = self.events.iter().map(EventKind::to_string).collect();
+            params.push("event", events.join(" OR "));
+        }
+        params.push_opt("recipient", self.recipient.as_ref());
+        params.push_opt("message-id", self.message_id.as_ref());
+        if !self.tags.is_empty() {
+            params.push("tags", self.tags.join(" OR "));
+        }
+        params.push_opt("begin", self.begin.map(httpdate::fmt_http_date));
+        params.push_opt("end", self.end.map(httpdate::fmt_http_date));
+        params.push_opt("ascending", self.ascending.map(crate::options::yes_no));
+        params.push_opt("limit", self.limit);
+        params
+    }
+}
+
+/// Walks the pages of an event query, one event at a time.
+struct EventCursor {
+    next: Option<NextPage>,
+    items: std::vec::IntoIter<Event>,
+}
+
+enum NextPage {
+    Query(EventQuery),
+    Url(String),
+}
+
+impl EventCursor {
+    fn new(query: &EventQuery) -> Self {
+        EventCursor {
+            next: Some(NextPage::Query(query.clone())),
+            items: Vec::new().into_iter(),
+        }
+    }
+
+    async fn advance(&mut self, client: &Mailgun) -> Option<SendResult<Event>> {
+        self.advance_with(|next| async move {
+            match next {
+                NextPage::Query(query) => client.get_events(&query).await,
+                NextPage::Url(url) => client.execute(client.request_url(Method::GET, &url)).await,
+            }
+        })
+        .await
+    }
+
+    /// Does the work of `advance`, fetching pages with `fetch`.
+    async fn advance_with<F, Fut>(&mut self, mut fetch: F) -> Option<SendResult<Event>>
+    where
+        F: FnMut(NextPage) -> Fut,
+        Fut: Future<Output = SendResult<Page<Event>>>,
+    {
+        loop {
+            if let Some(event) = self.items.next() {
+                return Some(Ok(event));
+            }
+            let page = match fetch(self.next.take()?).await {
+                Ok(page) => page,
+                Err(err) => return Some(Err(err)),
+            };
+            // Mailgun keeps returning a `next` link; an empty page is the end.
+            if page.items.is_empty() {
+                return None;
+            }
+            self.next = page.paging.next.map(NextPage::Url);
+            self.items = page.items.into_iter();
+        }
+    }
+}
+
+impl Mailgun {
+    /// Fetches the first page of events for this client's domain.
+    /// Use [`Mailgun::next_page`] or [`Mailgun::events`] for the rest.
+    pub async fn get_events(&self, query: &EventQuery) -> SendResult<Page<Event>> {
+        let path = format!("v3/{}/events", self.domain);
+        self.execute(self.request(Method::GET, &path).query(&query.params()))
+            .await
+    }
+
+    /// Streams every event matching `query`, following the paging links.
+    /// The stream ends after the first error.
+    pub fn events<'a>(&'a self, query: &EventQuery) -> impl Stream<Item = SendResult<Event>> + 'a {
+        stream::unfold(EventCursor::new(query), move |mut cursor| async move {
+            cursor.advance(self).await.map(|event| (event, cursor))
+        })
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn get_events(&self, query: &EventQuery) -> SendResult<Page<Event>> {
+        self.runtime.block_on(self.inner.get_events(query))
+    }
+
+    /// Iterates over every event matching `query`, following the paging links.
+    /// The iterator ends after the first error.
+    pub fn events(&self, query: &EventQuery) -> Events<'_> {
+        Events {
+            client: self,
+            cursor: EventCursor::new(query),
+        }
+    }
+}
+
+/// The iterator returned by [`crate::blocking::Mailgun::events`].
+#[cfg(feature = "blocking")]
+pub struct Events<'a> {
+    client: &'a crate::blocking::Mailgun,
+    cursor: EventCursor,
+}
+
+#[cfg(feature = "blocking")]
+impl Iterator for Events<'_> {
+    type Item = SendResult<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let client = self.client;
+        client.runtime.block_on(self.cursor.advance(&client.inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Paging};
+    use futures_util::FutureExt;
+    use serde_json::json;
+
+    fn event(value: Value) -> Event {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn base(name: &str) -> Value {
+        json!({
+            "event": name,
+            "id": "W3X4JOhFT-OZidZGKKr9iA",
+            "timestamp": 1521472262.908181,
+            "log-level": "info",
+            "recipient": "alice@example.com",
+            "recipient-domain": "example.com",
+            "message": {
+                "headers": {
+                    "to": "Alice <alice@example.com>",
+                    "message-id": "20130503182626.18666.16540@example.com",
+                    "from": "Bob <bob@example.com>",
+                    "subject": "Test"
+                },
+                "attachments": [],
+                "size": 111
+            },
+            "tags": ["newsletter"],
+            "user-variables": { "order": "42" }
+        })
+    }
+
+    fn with(name: &str, extra: Value) -> Value {
+        let mut value = base(name);
+        for (key, field) in extra.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        value
+    }
+
+    #[test]
+    fn deserializes_delivery_events() {
+        let extra = json!({
+            "envelope": { "transport": "smtp", "sender": "bob@example.com", "targets": "alice@example.com" },
+            "delivery-status": { "code": 250, "message": "OK", "attempt-no": 1, "tls": true }
+        });
+        for name in &["accepted", "delivered"] {
+            let parsed = event(with(name, extra.clone()));
+            let delivery = match (&parsed, *name) {
+                (Event::Accepted(delivery), "accepted") => delivery,
+                (Event::Delivered(delivery), "delivered") => delivery,
+                _ => panic!("unexpected {:?}", parsed),
+            };
+            assert_eq!(delivery.info.id, "W3X4JOhFT-OZidZGKKr9iA");
+            assert_eq!(delivery.info.tags, vec!["newsletter"]);
+            assert_eq!(delivery.info.user_variables["order"], json!("42"));
+            assert_eq!(
+                delivery
+                    .info
+                    .message
+                    .as_ref()
+                    .unwrap()
+                    .headers
+                    .subject
+                    .as_deref(),
+                Some("Test")
+            );
+            assert_eq!(delivery.delivery_status.as_ref().unwrap().code, Some(250));
+            assert_eq!(
+                delivery.envelope.as_ref().unwrap().targets.as_deref(),
+                Some("alice@example.com")
+            );
+        }
+    }
+
+    #[test]
+    fn deserializes_rejected() {
+        let parsed = event(with(
+            "rejected",
+            json!({ "reject": { "reason": "hardfail", "description": "bad domain" } }),
+        ));
+        match parsed {
+            Event::Rejected(rejection) => {
+                assert_eq!(
+                    rejection.reject.unwrap().reason.as_deref(),
+                    Some("hardfail")
+                )
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserializes_failed() {
+        let parsed = event(with(
+            "failed",
+            json!({
+                "severity": "permanent",
+                "reason": "bounce",
+                "delivery-status": { "code": 550, "enhanced-code": "5.1.1", "message": "No such user" }
+            }),
+        ));
+        match parsed {
+            Event::Failed(failure) => {
+                assert!(failure.is_permanent());
+                assert_eq!(failure.reason.as_deref(), Some("bounce"));
+                assert_eq!(
+                    failure.delivery_status.unwrap().enhanced_code.as_deref(),
+                    Some("5.1.1")
+                );
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let parsed = event(with("failed", json!({ "severity": "temporary" })));
+        match parsed {
+            Event::Failed(failure) => assert!(!failure.is_permanent()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserializes_engagement_events() {
+        let extra = json!({
+            "ip": "50.56.129.169",
+            "geolocation": { "country": "US", "region": "CA", "city": "San Francisco" },
+            "client-info": { "client-type": "browser", "client-os": "Linux", "device-type": "desktop" },
+            "url": "https://example.com/offer"
+        });
+        for name in &["opened", "clicked", "unsubscribed", "complained"] {
+            let parsed = event(with(name, extra.clone()));
+            let engagement = match (&parsed, *name) {
+                (Event::Opened(engagement), "opened")
+                | (Event::Clicked(engagement), "clicked")
+                | (Event::Unsubscribed(engagement), "unsubscribed")
+                | (Event::Complained(engagement), "complained") => engagement,
+                _ => panic!("unexpected {:?}", parsed),
+            };
+            assert_eq!(engagement.ip.as_deref(), Some("50.56.129.169"));
+            assert_eq!(
+                engagement.geolocation.as_ref().unwrap().city.as_deref(),
+                Some("San Francisco")
+            );
+            assert_eq!(
+                engagement
+                    .client_info
+                    .as_ref()
+                    .unwrap()
+                    .client_os
+                    .as_deref(),
+                Some("Linux")
+            );
+            assert_eq!(engagement.url.as_deref(), Some("https://example.com/offer"));
+            assert_eq!(
+                parsed.info().unwrap().recipient.as_deref(),
+                Some("alice@example.com")
+            );
+        }
+    }
+
+    #[test]
+    fn deserializes_stored() {
+        let parsed = event(with(
+            "stored",
+            json!({ "storage": { "url": "https://storage.mailgun.net/v3/domains/x/messages/k", "key": "k" } }),
+        ));
+        match parsed {
+            Event::Stored(stored) => assert_eq!(stored.storage.unwrap().key, "k"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserializes_unknown_event_types() {
+        let parsed = event(base("list_member_uploaded"));
+        assert_eq!(parsed, Event::Unknown);
+        assert!(parsed.info().is_none());
+    }
+
+    #[test]
+    fn accepts_integer_timestamps() {
+        let parsed = event(json!({ "event": "delivered", "id": "x", "timestamp": 1521472262 }));
+        assert_eq!(parsed.info().unwrap().timestamp, 1521472262.0);
+    }
+
+    #[test]
+    fn requires_the_shared_fields() {
+        assert!(serde_json::from_value::<Event>(json!({ "event": "delivered" })).is_err());
+    }
+
+    fn page(ids: &[&str], next: Option<&str>) -> SendResult<Page<Event>> {
+        Ok(Page {
+            items: ids
+                .iter()
+                .map(|id| event(json!({ "event": "delivered", "id": id, "timestamp": 0.0 })))
+                .collect(),
+            paging: Paging {
+                next: next.map(str::to_string),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Drains a cursor over `pages`, recording which pages were requested.
+    fn drain(pages: Vec<SendResult<Page<Event>>>) -> (Vec<Result<String, String>>, Vec<String>) {
+        let mut pages = pages.into_iter();
+        let mut requested = Vec::new();
+        let mut results = Vec::new();
+        let mut cursor = EventCursor::new(&EventQuery::default());
+        loop {
+            let next = cursor
+                .advance_with(|next| {
+                    requested.push(match next {
+                        NextPage::Query(_) => String::from("query"),
+                        NextPage::Url(url) => url,
+                    });
+                    let page = pages.next().expect("requested a page past the end");
+                    async move { page }
+                })
+                .now_or_never()
+                .unwrap();
+            match next {
+                Some(Ok(event)) => results.push(Ok(event.info().unwrap().id.clone())),
+                Some(Err(err)) => results.push(Err(err.to_string())),
+                None => return (results, requested),
+            }
+        }
+    }
+
+    #[test]
+    fn cursor_follows_next_links_until_an_empty_page() {
+        let (results, requested) = drain(vec![
+            page(&["a", "b"], Some("page-2")),
+            page(&["c"], Some("page-3")),
+            page(&[], Some("page-4")),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                Ok("a".to_string()),
+                Ok("b".to_string()),
+                Ok("c".to_string())
+            ]
+        );
+        assert_eq!(requested, vec!["query", "page-2", "page-3"]);
+    }
+
+    #[test]
+    fn cursor_stops_without_a_next_link() {
+        let (results, requested) = drain(vec![page(&["a"], None)]);
+        assert_eq!(results, vec![Ok("a".to_string())]);
+        assert_eq!(requested, vec!["query"]);
+    }
+
+    #[test]
+    fn cursor_stops_after_the_first_error() {
+        let (results, requested) =
+            drain(vec![page(&["a"], Some("page-2")), Err(Error::Unauthorized)]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], Ok("a".to_string()));
+        assert!(results[1].is_err());
+        assert_eq!(requested, vec!["query", "page-2"]);
+    }
+}
@@ -0,0 +1,614 @@
+//! Inbound mail: the messages a route posts to your endpoint with `forward()`.
+
+use crate::webhooks::{Signature, VerificationError, WebhookVerifier};
+use crate::{Attachment, EmailAddress};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A message received through a route. This is the receiving side of
+/// [`Message`](crate::Message).
+#[derive(Clone, Debug, PartialEq)]
+pub struct InboundMessage {
+    /// The envelope sender (`MAIL FROM`). `None` for the empty sender of a
+    /// bounce, or if it is not a single address.
+    pub sender: Option<EmailAddress>,
+    /// The envelope recipient (`RCPT TO`) that matched the route, or `None`
+    /// if it is not a single address.
+    pub recipient: Option<EmailAddress>,
+    /// The raw `From` header, which may hold several addresses or a group.
+    /// See [`InboundMessage::from_address`].
+    pub from: Option<String>,
+    pub subject: Option<String>,
+    pub body_plain: Option<String>,
+    /// The text body without quoted replies or the signature.
+    pub stripped_text: Option<String>,
+    pub stripped_signature: Option<String>,
+    pub body_html: Option<String>,
+    /// The HTML body without quoted replies.
+    pub stripped_html: Option<String>,
+    /// All MIME headers, in the order they appeared.
+    pub headers: Vec<(String, String)>,
+    pub attachments: Vec<Attachment>,
+    /// Maps a Content-ID, without angle brackets, to its index in `attachments`.
+    pub content_ids: HashMap<String, usize>,
+    /// Check it with [`WebhookVerifier::verify_inbound`] before trusting the
+    /// message.
+    pub signature: Option<Signature>,
+}
+
+/// Why an inbound message could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InboundError {
+    /// The body is neither `multipart/form-data` nor
+    /// `application/x-www-form-urlencoded`.
+    UnsupportedContentType(String),
+    /// The multipart body is cut short or has no boundary.
+    Malformed,
+    /// A required field is not present.
+    MissingField(&'static str),
+    /// A field that should hold JSON could not be read.
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for InboundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InboundError::UnsupportedContentType(content_type) => {
+                write!(f, "unsupported content type: {}", content_type)
+            }
+            InboundError::Malformed => write!(f, "malformed multipart body"),
+            InboundError::MissingField(name) => write!(f, "missing field: {}", name),
+            InboundError::InvalidField(name) => write!(f, "invalid field: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for InboundError {}
+
+impl InboundMessage {
+    /// Parses a request body, given the request's `Content-Type` header.
+    /// Mailgun sends multipart bodies when the message has attachments and
+    /// urlencoded bodies otherwise.
+    pub fn parse(content_type: &str, body: &[u8]) -> Result<Self, InboundError> {
+        let (mime, params) = split_header(content_type);
+        let parts = match mime.to_ascii_lowercase().as_str() {
+            "multipart/form-data" => {
+                let boundary = param(&params, "boundary").ok_or(InboundError::Malformed)?;
+                parse_multipart(body, boundary)?
+            }
+            "application/x-www-form-urlencoded" => form_urlencoded::parse(body)
+                .map(|(name, value)| Part {
+                    name: name.into_owned(),
+                    filename: None,
+                    content_type: None,
+                    data: value.into_owned().into_bytes(),
+                })
+                .collect(),
+            _ => {
+                return Err(InboundError::UnsupportedContentType(
+                    content_type.to_string(),
+                ))
+            }
+        };
+        InboundMessage::from_parts(parts)
+    }
+
+    /// Looks up a header by name, ignoring case. Returns the first match.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `From` header as a single address, if it is one.
+    pub fn from_address(&self) -> Option<EmailAddress> {
+        self.from.as_ref()?.parse().ok()
+    }
+
+    /// Returns the inline attachment referenced as `cid:<content_id>`.
+    pub fn inline(&self, content_id: &str) -> Option<&Attachment> {
+        self.content_ids
+            .get(content_id)
+            .and_then(|&index| self.attachments.get(index))
+    }
+
+    fn from_parts(parts: Vec<Part>) -> Result<Self, InboundError> {
+        let mut fields = HashMap::new();
+        let mut attachments = Vec::new();
+        for part in parts {
+            let number = part
+                .name
+                .strip_prefix("attachment-")
+                .and_then(|n| n.parse::<usize>().ok());
+            match (number, part.filename) {
+                (Some(number), Some(filename)) => {
+                    let mut attachment = Attachment::from_bytes(&filename, part.data);
+                    if let Some(content_type) = part.content_type {
+                        attachment = attachment.with_content_type(&content_type);
+                    }
+                    attachments.push((number, part.name, attachment));
+                }
+                _ => {
+                    let value = String::from_utf8_lossy(&part.data).into_owned();
+                    fields.entry(part.name).or_insert(value);
+                }
+            }
+        }
+        attachments.sort_by_key(|(number, _, _)| *number);
+
+        let mut content_ids = HashMap::new();
+        if let Some(map) = fields.get("content-id-map") {
+            let map: HashMap<String, String> = serde_json::from_str(map)
+                .map_err(|_| InboundError::InvalidField("content-id-map"))?;
+            for (content_id, field) in map {
+                let content_id = content_id.trim_start_matches('<').trim_end_matches('>');
+                if let Some(index) = attachments.iter().position(|(_, name, _)| *name == field) {
+                    content_ids.insert(content_id.to_string(), index);
+                }
+            }
+        }
+
+        let headers = match fields.get("message-headers") {
+            Some(headers) => serde_json::from_str(headers)
+                .map_err(|_| InboundError::InvalidField("message-headers"))?,
+            None => Vec::new(),
+        };
+
+        let signature = match (
+            fields.remove("timestamp"),
+            fields.remove("token"),
+            fields.remove("signature"),
+        ) {
+            (Some(timestamp), Some(token), Some(signature)) => Some(Signature {
+                timestamp,
+                token,
+                signature,
+            }),
+            _ => None,
+        };
+
+        // Both are always posted, though `sender` is empty for bounces.
+        let mut address = |name: &'static str| {
+            fields
+                .remove(name)
+                .map(|value| value.parse().ok())
+                .ok_or(InboundError::MissingField(name))
+        };
+        Ok(InboundMessage {
+            sender: address("sender")?,
+            recipient: address("recipient")?,
+            from: fields.remove("from"),
+            subject: fields.remove("subject"),
+            body_plain: fields.remove("body-plain"),
+            stripped_text: fields.remove("stripped-text"),
+            stripped_signature: fields.remove("stripped-signature"),
+            body_html: fields.remove("body-html"),
+            stripped_html: fields.remove("stripped-html"),
+            headers,
+            attachments: attachments
+                .into_iter()
+                .map(|(_, _, attachment)| attachment)
+                .collect(),
+            content_ids,
+            signature,
+        })
+    }
+}
+
+impl WebhookVerifier {
+    /// Verifies the signature of an inbound message. A message without one
+    /// is rejected with [`VerificationError::Unsigned`].
+    pub fn verify_inbound(&self, message: &InboundMessage) -> Result<(), VerificationError> {
+        match message.signature {
+            Some(ref signature) => self.verify(signature),
+            None => Err(VerificationError::Unsigned),
+        }
+    }
+}
+
+/// One field of a form body.
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Part>, InboundError> {
+    let delimiter = format!("\r\n--{}", boundary).into_bytes();
+    // The first delimiter may come without the leading line break.
+    let mut pos =
+        find(body, &delimiter[2..], 0).ok_or(InboundError::Malformed)? + delimiter.len() - 2;
+
+    let mut parts = Vec::new();
+    loop {
+        if body[pos..].starts_with(b"--") {
+            return Ok(parts);
+        }
+        if !body[pos..].starts_with(b"\r\n") {
+            return Err(InboundError::Malformed);
+        }
+        pos += 2;
+
+        let end = find(body, &delimiter, pos).ok_or(InboundError::Malformed)?;
+        let split = find(&body[..end], b"\r\n\r\n", pos).ok_or(InboundError::Malformed)?;
+        let head = String::from_utf8_lossy(&body[pos..split]);
+
+        let mut name = None;
+        let mut filename = None;
+        let mut content_type = None;
+        for line in head.split("\r\n") {
+            let (header, value) = match line.split_once(':') {
+                Some(header) => header,
+                None => continue,
+            };
+            if header.trim().eq_ignore_ascii_case("content-disposition") {
+                let (_, params) = split_header(value);
+                name = param(&params, "name").map(str::to_string);
+                filename = param(&params, "filename").map(str::to_string);
+            } else if header.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            }
+        }
+
+        if let Some(name) = name {
+            parts.push(Part {
+                name,
+                filename,
+                content_type,
+                data: body[split + 4..end].to_vec(),
+            });
+        }
+        pos = end + delimiter.len();
+    }
+}
+
+/// Splits a header value such as `form-data; name="file"; filename="a;b.txt"`
+/// into its first item and its parameters, unquoting parameter values.
+fn split_header(value: &str) -> (String, Vec<(String, String)>) {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                current.push(c);
+                escaped = false;
+            }
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    let mut items = items.into_iter();
+    let first = items.next().unwrap_or_default().trim().to_string();
+    let params = items
+        .filter_map(|item| {
+            let (key, value) = item.split_once('=')?;
+            Some((key.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    (first, params)
+}
+
+fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "----Boundary7MA4YWxk";
+
+    /// Builds a multipart body from `(disposition params, content type, data)` parts.
+    fn multipart(parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (params, content_type, data) in parts {
+            body.extend(format!("--{}\r\n", BOUNDARY).bytes());
+            body.extend(format!("Content-Disposition: form-data; {}\r\n", params).bytes());
+            if let Some(content_type) = content_type {
+                body.extend(format!("Content-Type: {}\r\n", content_type).bytes());
+            }
+            body.extend(b"\r\n");
+            body.extend(*data);
+            body.extend(b"\r\n");
+        }
+        body.extend(format!("--{}--\r\n", BOUNDARY).bytes());
+        body
+    }
+
+    fn field<'a>(name: &str, value: &'a str) -> (String, Option<&'static str>, &'a [u8]) {
+        (format!("name=\"{}\"", name), None, value.as_bytes())
+    }
+
+    fn parse_multipart_body(
+        parts: &[(String, Option<&str>, &[u8])],
+    ) -> Result<InboundMessage, InboundError> {
+        let parts: Vec<_> = parts
+            .iter()
+            .map(|(params, content_type, data)| (params.as_str(), *content_type, *data))
+            .collect();
+        let content_type = format!("multipart/form-data; boundary=\"{}\"", BOUNDARY);
+        InboundMessage::parse(&content_type, &multipart(&parts))
+    }
+
+    fn urlencoded(body: &str) -> Result<InboundMessage, InboundError> {
+        InboundMessage::parse("application/x-www-form-urlencoded", body.as_bytes())
+    }
+
+    #[test]
+    fn parses_urlencoded_bodies() {
+        let message = urlencoded(
+            "sender=bob%40example.com&recipient=support%40example.com\
+             &from=Bob+Smith+%3Cbob%40example.com%3E&subject=Re%3A+help+%26+more\
+             &body-plain=Hi%0D%0Athere&stripped-text=Hi&stripped-signature=Bob\
+             &body-html=%3Cp%3EHi%3C%2Fp%3E&stripped-html=%3Cp%3EHi%3C%2Fp%3E\
+             &message-headers=%5B%5B%22Subject%22%2C+%22Re%3A+help%22%5D%2C+%5B%22X-Mailgun-Spam%22%2C+%22No%22%5D%5D\
+             &timestamp=1529006854&token=abc&signature=def",
+        )
+        .unwrap();
+
+        assert_eq!(
+            message.sender,
+            Some(EmailAddress::address("bob@example.com"))
+        );
+        assert_eq!(
+            message.recipient,
+            Some(EmailAddress::address("support@example.com"))
+        );
+        assert_eq!(message.from.as_deref(), Some("Bob Smith <bob@example.com>"));
+        assert_eq!(
+            message.from_address(),
+            Some(EmailAddress::name_address("Bob Smith", "bob@example.com"))
+        );
+        assert_eq!(message.subject.as_deref(), Some("Re: help & more"));
+        assert_eq!(message.body_plain.as_deref(), Some("Hi\r\nthere"));
+        assert_eq!(message.stripped_text.as_deref(), Some("Hi"));
+        assert_eq!(message.stripped_signature.as_deref(), Some("Bob"));
+        assert_eq!(message.body_html.as_deref(), Some("<p>Hi</p>"));
+        assert_eq!(message.stripped_html.as_deref(), Some("<p>Hi</p>"));
+        assert_eq!(message.header("subject"), Some("Re: help"));
+        assert_eq!(message.header("x-mailgun-spam"), Some("No"));
+        assert_eq!(message.header("missing"), None);
+        assert!(message.attachments.is_empty());
+        assert_eq!(
+            message.signature,
+            Some(Signature {
+                timestamp: String::from("1529006854"),
+                token: String::from("abc"),
+                signature: String::from("def"),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_multipart_bodies_with_attachments() {
+        let binary: &[u8] = &[0, 159, 13, 10, 45, 45, 255];
+        let message = parse_multipart_body(&[
+            field("sender", "bob@example.com"),
+            field("recipient", "support@example.com"),
+            field("subject", "Photos"),
+            field("body-plain", "line one\r\nline two\r\n"),
+            field("attachment-count", "3"),
+            field(
+                "content-id-map",
+                r#"{"<logo@example.com>": "attachment-2"}"#,
+            ),
+            (
+                String::from(r#"name="attachment-10"; filename="notes.txt""#),
+                None,
+                b"ten",
+            ),
+            (
+                String::from(r#"name="attachment-2"; filename="logo.png""#),
+                Some("image/png"),
+                binary,
+            ),
+            (
+                String::from(r#"name="attachment-1"; filename="a; \"quoted\" name.pdf""#),
+                Some("application/pdf"),
+                b"%PDF",
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(message.subject.as_deref(), Some("Photos"));
+        assert_eq!(
+            message.body_plain.as_deref(),
+            Some("line one\r\nline two\r\n")
+        );
+
+        let names: Vec<&str> = message
+            .attachments
+            .iter()
+            .map(|attachment| attachment.filename.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![r#"a; "quoted" name.pdf"#, "logo.png", "notes.txt"]
+        );
+        assert_eq!(message.attachments[0].content_type, "application/pdf");
+        assert_eq!(message.attachments[1].data, binary);
+        // Without a Content-Type the type is guessed from the filename.
+        assert_eq!(message.attachments[2].content_type, "text/plain");
+
+        assert_eq!(message.content_ids.len(), 1);
+        assert_eq!(
+            message
+                .inline("logo@example.com")
+                .map(|a| a.filename.as_str()),
+            Some("logo.png")
+        );
+        assert!(message.inline("<logo@example.com>").is_none());
+        assert!(message.inline("missing").is_none());
+    }
+
+    #[test]
+    fn accepts_unquoted_boundaries_and_header_case() {
+        let body = format!(
+            "--{b}\r\ncontent-disposition: form-data; name=sender\r\n\r\nbob@example.com\r\n\
+             --{b}\r\nCONTENT-DISPOSITION: form-data; name=\"recipient\"\r\n\r\nsupport@example.com\r\n\
+             --{b}--",
+            b = "simple"
+        );
+        let message =
+            InboundMessage::parse("Multipart/Form-Data;boundary=simple", body.as_bytes()).unwrap();
+        assert_eq!(
+            message.sender,
+            Some(EmailAddress::address("bob@example.com"))
+        );
+        assert_eq!(
+            message.recipient,
+            Some(EmailAddress::address("support@example.com"))
+        );
+    }
+
+    #[test]
+    fn ignores_content_id_entries_without_an_attachment() {
+        let message = parse_multipart_body(&[
+            field("sender", "bob@example.com"),
+            field("recipient", "support@example.com"),
+            field(
+                "content-id-map",
+                r#"{"<gone@example.com>": "attachment-5"}"#,
+            ),
+        ])
+        .unwrap();
+        assert!(message.content_ids.is_empty());
+    }
+
+    #[test]
+    fn rejects_cut_off_bodies() {
+        let body = multipart(&[
+            (r#"name="sender""#, None, b"bob@example.com"),
+            (r#"name="recipient""#, None, b"support@example.com"),
+            (r#"name="attachment-1"; filename="a.txt""#, None, b"data"),
+        ]);
+        let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+        assert!(InboundMessage::parse(&content_type, &body).is_ok());
+
+        // Every prefix short of the closing delimiter is incomplete.
+        let end = body.len() - "--\r\n".len();
+        for len in 0..end {
+            assert_eq!(
+                InboundMessage::parse(&content_type, &body[..len]),
+                Err(InboundError::Malformed),
+                "prefix of {} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_missing_boundaries_and_other_types() {
+        assert_eq!(
+            InboundMessage::parse("multipart/form-data", b"--x--"),
+            Err(InboundError::Malformed)
+        );
+        assert_eq!(
+            InboundMessage::parse("application/json", b"{}"),
+            Err(InboundError::UnsupportedContentType(String::from(
+                "application/json"
+            )))
+        );
+    }
+
+    #[test]
+    fn accepts_a_null_sender() {
+        let message =
+            urlencoded("sender=&recipient=support%40example.com&from=MAILER-DAEMON%40example.com")
+                .unwrap();
+        assert_eq!(message.sender, None);
+        assert_eq!(
+            message.recipient,
+            Some(EmailAddress::address("support@example.com"))
+        );
+        assert_eq!(
+            message.from_address(),
+            Some(EmailAddress::address("MAILER-DAEMON@example.com"))
+        );
+
+        let message = urlencoded("sender=%3C%3E&recipient=support%40example.com").unwrap();
+        assert_eq!(message.sender, None);
+    }
+
+    #[test]
+    fn keeps_from_headers_that_are_not_a_single_address() {
+        for from in &["a@x.com, b@y.com", "undisclosed-recipients:;"] {
+            let body: String = form_urlencoded::Serializer::new(String::new())
+                .append_pair("sender", "bob@example.com")
+                .append_pair("recipient", "a@x.com, b@y.com")
+                .append_pair("from", from)
+                .finish();
+            let message = urlencoded(&body).unwrap();
+            assert_eq!(message.from.as_deref(), Some(*from));
+            assert_eq!(message.from_address(), None);
+            assert_eq!(message.recipient, None);
+            assert_eq!(
+                message.sender,
+                Some(EmailAddress::address("bob@example.com"))
+            );
+        }
+    }
+
+    #[test]
+    fn requires_sender_and_recipient_fields() {
+        assert_eq!(
+            urlencoded("recipient=support%40example.com"),
+            Err(InboundError::MissingField("sender"))
+        );
+        assert_eq!(
+            urlencoded("sender=bob%40example.com"),
+            Err(InboundError::MissingField("recipient"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_json_fields() {
+        assert_eq!(
+            urlencoded("sender=&recipient=&message-headers=nope"),
+            Err(InboundError::InvalidField("message-headers"))
+        );
+        assert_eq!(
+            urlencoded("sender=&recipient=&content-id-map=%5B%5D"),
+            Err(InboundError::InvalidField("content-id-map"))
+        );
+    }
+
+    #[test]
+    fn verify_inbound_rejects_unsigned_messages() {
+        let verifier = WebhookVerifier::new("key");
+        let message = urlencoded("sender=&recipient=support%40example.com").unwrap();
+        assert_eq!(message.signature, None);
+        assert_eq!(
+            verifier.verify_inbound(&message),
+            Err(VerificationError::Unsigned)
+        );
+
+        let partial = urlencoded("sender=&recipient=&timestamp=1&token=abc").unwrap();
+        assert_eq!(partial.signature, None);
+
+        let signed = urlencoded("sender=&recipient=&timestamp=1&token=abc&signature=00").unwrap();
+        assert_eq!(
+            verifier.verify_inbound(&signed),
+            Err(VerificationError::Expired)
+        );
+    }
+}
@@ -1,19 +1,71 @@
-use reqwest::Error as ReqError;
+use reqwest::multipart::Form;
+use reqwest::{Method, RequestBuilder};
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::Deserialize;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::str::FromStr;
 
-const MAILGUN_API: &str = "https://api.mailgun.net/v3";
+const MAILGUN_US_API: &str = "https://api.mailgun.net";
+const MAILGUN_EU_API: &str = "https://api.eu.mailgun.net";
 const MESSAGES_ENDPOINT: &str = "messages";
+const MIME_ENDPOINT: &str = "messages.mime";
+/// The most recipients Mailgun accepts in a single batch send.
+const BATCH_LIMIT: usize = 1000;
 
-#[derive(Default)]
+mod attachment;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+pub mod domains;
+mod error;
+pub mod events;
+pub mod inbound;
+mod options;
+mod paging;
+mod params;
+pub mod routes;
+pub mod templates;
+pub mod webhooks;
+
+pub use attachment::Attachment;
+pub use builder::MessageBuilder;
+pub use error::Error;
+pub use options::{SendOptions, TrackingClicks};
+pub use paging::{Page, Paging};
+use params::Params;
+
+/// An async Mailgun client.
+///
+/// The client holds a pooled `reqwest::Client`, so it should be created once
+/// and cloned (cheaply) wherever it is needed.
+#[derive(Clone)]
 pub struct Mailgun {
-    pub api_key: String,
-    pub domain: String,
-    pub message: Message,
+    api_key: String,
+    domain: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+/// The region a Mailgun domain is hosted in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Region {
+    #[default]
+    US,
+    EU,
+}
+
+impl Region {
+    pub fn base_url(self) -> &'static str {
+        match self {
+            Region::US => MAILGUN_US_API,
+            Region::EU => MAILGUN_EU_API,
+        }
+    }
 }
 
-pub type SendResult<T> = Result<T, ReqError>;
+pub type SendResult<T> = Result<T, Error>;
 
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct SendResponse {
@@ -21,23 +73,193 @@ pub struct SendResponse {
     pub id: String,
 }
 
+/// The result of each request made by [`Mailgun::send_batch`], in order.
+/// Request `i` covers recipients `i * 1000` up to the next thousand. Sending
+/// stops at the first failed request, so a failure is always the last entry
+/// and the recipients after it were not sent to.
+#[derive(Debug)]
+pub struct BatchResponse {
+    pub responses: Vec<SendResult<SendResponse>>,
+}
+
+impl BatchResponse {
+    /// Whether every recipient was sent to.
+    pub fn is_complete(&self) -> bool {
+        self.responses.iter().all(Result::is_ok)
+    }
+}
+
 impl Mailgun {
-    pub fn send(self, sender: &EmailAddress) -> SendResult<SendResponse> {
-        let client = reqwest::blocking::Client::new();
-        let mut params = self.message.params();
-        params.insert("from".to_string(), sender.to_string());
-        let url = format!("{}/{}/{}", MAILGUN_API, self.domain, MESSAGES_ENDPOINT);
+    pub fn new(api_key: &str, domain: &str) -> Self {
+        Mailgun::with_client(api_key, domain, reqwest::Client::new())
+    }
+
+    /// Creates a client that sends through an existing `reqwest::Client`.
+    pub fn with_client(api_key: &str, domain: &str, client: reqwest::Client) -> Self {
+        Mailgun {
+            api_key: api_key.to_string(),
+            domain: domain.to_string(),
+            base_url: Region::US.base_url().to_string(),
+            client,
+        }
+    }
+
+    pub fn with_region(self, region: Region) -> Self {
+        self.with_base_url(region.base_url())
+    }
+
+    /// Sends requests to `base_url` instead of a Mailgun region, e.g. a proxy
+    /// or a local mock server. The URL must not include the `/v3` version.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    /// Starts an authenticated request to `path`, relative to the base URL.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request_url(method, &self.url(path))
+    }
+
+    /// Starts an authenticated request to an absolute URL, such as a paging link.
+    fn request_url(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client
+            .request(method, url)
+            .basic_auth("api", Some(&self.api_key))
+    }
+
+    async fn execute<T: DeserializeOwned>(&self, request: RequestBuilder) -> SendResult<T> {
+        let res = request.send().await?;
+        Mailgun::parse(res).await
+    }
 
-        let res = client
-            .post(url)
-            .basic_auth("api", Some(self.api_key))
-            .form(&params)
-            .send()?
-            .error_for_status()?;
+    /// Like `execute`, for responses that only acknowledge the request.
+    async fn execute_empty(&self, request: RequestBuilder) -> SendResult<()> {
+        let _: IgnoredAny = self.execute(request).await?;
+        Ok(())
+    }
 
-        let parsed: SendResponse = res.json()?;
-        Ok(parsed)
+    pub async fn send(&self, sender: &EmailAddress, message: &Message) -> SendResult<SendResponse> {
+        message.options.validate()?;
+        self.post_message(sender, message, &message.to, Params::new())
+            .await
+    }
+
+    /// Sends `message` to every recipient, substituting each recipient's data
+    /// for `%recipient.name%` placeholders. Build the message with
+    /// [`MessageBuilder::build_batch`]: one with `to`, `cc` or `bcc` recipients
+    /// is rejected, since they would get one copy per request with the
+    /// placeholders left in. Each address may appear only once.
+    ///
+    /// Lists longer than Mailgun's limit of 1000 recipients are split into
+    /// several requests. An `Err` means the batch was rejected before anything
+    /// was sent; a failed request is reported in [`BatchResponse::responses`],
+    /// after the requests that went through before it.
+    pub async fn send_batch(
+        &self,
+        sender: &EmailAddress,
+        message: &Message,
+        recipients: &[(EmailAddress, Value)],
+    ) -> SendResult<BatchResponse> {
+        if recipients.is_empty() {
+            return Err(Error::InvalidMessage(String::from(
+                "a batch needs at least one recipient",
+            )));
+        }
+        if !message.to.is_empty() || !message.cc.is_empty() || !message.bcc.is_empty() {
+            return Err(Error::InvalidMessage(String::from(
+                "a batch message cannot have to, cc or bcc recipients",
+            )));
+        }
+        let mut seen = HashSet::new();
+        if let Some((address, _)) = recipients
+            .iter()
+            .find(|(address, _)| !seen.insert(address.address.as_str()))
+        {
+            return Err(Error::InvalidMessage(format!(
+                "{} appears more than once in the batch",
+                address.address
+            )));
+        }
+        message.options.validate()?;
+
+        let mut responses = Vec::new();
+        for (to, params) in batch_requests(recipients) {
+            let response = self.post_message(sender, message, &to, params).await;
+            let failed = response.is_err();
+            responses.push(response);
+            if failed {
+                break;
+            }
+        }
+        Ok(BatchResponse { responses })
     }
+
+    /// Sends a message that is already encoded as MIME.
+    pub async fn send_mime(&self, message: &MimeMessage) -> SendResult<SendResponse> {
+        if message.to.is_empty() {
+            return Err(Error::InvalidMessage(String::from(
+                "a MIME message needs at least one recipient",
+            )));
+        }
+        message.options.validate()?;
+        let path = format!("v3/{}/{}", self.domain, MIME_ENDPOINT);
+        let form = message.form()?;
+
+        self.execute(self.request(Method::POST, &path).multipart(form))
+            .await
+    }
+
+    async fn post_message(
+        &self,
+        sender: &EmailAddress,
+        message: &Message,
+        to: &[EmailAddress],
+        params: Params,
+    ) -> SendResult<SendResponse> {
+        let path = format!("v3/{}/{}", self.domain, MESSAGES_ENDPOINT);
+        let form = message.form(sender, to, params)?;
+
+        self.execute(self.request(Method::POST, &path).multipart(form))
+            .await
+    }
+
+    async fn parse<T: DeserializeOwned>(res: reqwest::Response) -> SendResult<T> {
+        if !res.status().is_success() {
+            return Err(Error::from_response(res).await);
+        }
+        Ok(res.json().await?)
+    }
+}
+
+/// Splits a batch into requests of at most [`BATCH_LIMIT`] recipients, each
+/// with the `recipient-variables` for its recipients.
+fn batch_requests(recipients: &[(EmailAddress, Value)]) -> Vec<(Vec<EmailAddress>, Params)> {
+    recipients
+        .chunks(BATCH_LIMIT)
+        .map(|batch| {
+            let to = batch.iter().map(|(address, _)| address.clone()).collect();
+            let variables: serde_json::Map<String, Value> = batch
+                .iter()
+                .map(|(address, data)| (address.address.clone(), data.clone()))
+                .collect();
+
+            let mut params = Params::new();
+            params.push("recipient-variables", Value::Object(variables).to_string());
+            (to, params)
+        })
+        .collect()
 }
 
 #[derive(Default)]
@@ -45,54 +267,136 @@ pub struct Message {
     pub to: Vec<EmailAddress>,
     pub cc: Vec<EmailAddress>,
     pub bcc: Vec<EmailAddress>,
-    pub subject: String,
-    pub text: String,
-    pub html: String,
-    pub template: String,
-    pub template_vars: HashMap<String, String>,
+    pub subject: Option<String>,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub template: Option<String>,
+    /// Data for the template's Handlebars placeholders, sent as `t:variables`.
+    /// Any `Serialize` value can be converted with `serde_json::to_value`.
+    pub template_vars: Option<Value>,
+    /// The template version to render instead of the active one.
+    pub template_version: Option<String>,
+    /// Generate a text part from the rendered HTML template.
+    pub template_text: bool,
+    pub attachments: Vec<Attachment>,
+    /// Inline images, referenced from the HTML body as `cid:<filename>`.
+    pub inline: Vec<Attachment>,
+    /// Extra MIME headers such as `("Reply-To", "support@example.com")`, sent as `h:` fields.
+    pub headers: Vec<(String, String)>,
+    /// Custom data sent as `v:` fields. It comes back in webhooks and events.
+    pub user_variables: HashMap<String, Value>,
+    pub options: SendOptions,
 }
 
 impl Message {
-    fn params(self) -> HashMap<String, String> {
-        let mut params = HashMap::new();
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::new()
+    }
+
+    fn form(
+        &self,
+        sender: &EmailAddress,
+        to: &[EmailAddress],
+        mut params: Params,
+    ) -> reqwest::Result<Form> {
+        params.push("from", sender.to_string());
+        Message::add_recipients("to", to, &mut params);
+        self.add_params(&mut params);
 
-        Message::add_recipients("to", self.to, &mut params);
-        Message::add_recipients("cc", self.cc, &mut params);
-        Message::add_recipients("bcc", self.bcc, &mut params);
+        let mut form = params.into_form();
+        for attachment in &self.attachments {
+            form = form.part("attachment", attachment.part()?);
+        }
+        for image in &self.inline {
+            form = form.part("inline", image.part()?);
+        }
+        Ok(form)
+    }
+
+    fn add_params(&self, params: &mut Params) {
+        Message::add_recipients("cc", &self.cc, params);
+        Message::add_recipients("bcc", &self.bcc, params);
+
+        if let Some(ref subject) = self.subject {
+            params.push("subject", subject.as_str());
+        }
+        if let Some(ref text) = self.text {
+            params.push("text", text.as_str());
+        }
+        if let Some(ref html) = self.html {
+            params.push("html", html.as_str());
+        }
 
-        params.insert(String::from("subject"), self.subject);
+        for (name, value) in &self.headers {
+            params.push(format!("h:{}", name), value.as_str());
+        }
 
-        params.insert(String::from("text"), self.text);
-        params.insert(String::from("html"), self.html);
+        Message::add_user_variables(&self.user_variables, params);
+        self.options.add_params(params);
 
         // add template
-        if !self.template.is_empty() {
-            params.insert(String::from("template"), self.template);
-            params.insert(
-                String::from("h:X-Mailgun-Variables"),
-                serde_json::to_string(&self.template_vars).unwrap(),
-            );
+        if let Some(ref template) = self.template {
+            params.push("template", template.as_str());
+            if let Some(ref version) = self.template_version {
+                params.push("t:version", version.as_str());
+            }
+            if self.template_text {
+                params.push("t:text", "yes");
+            }
+            if let Some(ref template_vars) = self.template_vars {
+                params.push("t:variables", template_vars.to_string());
+            }
         }
+    }
 
-        params
+    fn add_user_variables(variables: &HashMap<String, Value>, params: &mut Params) {
+        for (name, value) in variables {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            params.push(format!("v:{}", name), value);
+        }
     }
 
-    fn add_recipients(
-        field: &str,
-        addresses: Vec<EmailAddress>,
-        params: &mut HashMap<String, String>,
-    ) {
+    fn add_recipients(field: &str, addresses: &[EmailAddress], params: &mut Params) {
         if !addresses.is_empty() {
             let joined = addresses
                 .iter()
                 .map(EmailAddress::to_string)
                 .collect::<Vec<String>>()
                 .join(",");
-            params.insert(field.to_owned(), joined);
+            params.push(field, joined);
         }
     }
 }
 
+/// A pre-built RFC 5322 message for [`Mailgun::send_mime`]. The sender,
+/// subject and body all come from `mime`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MimeMessage {
+    pub to: Vec<EmailAddress>,
+    pub mime: Vec<u8>,
+    /// As [`Message::user_variables`].
+    pub user_variables: HashMap<String, Value>,
+    pub options: SendOptions,
+}
+
+impl MimeMessage {
+    fn form(&self) -> reqwest::Result<Form> {
+        let mut params = Params::new();
+        Message::add_recipients("to", &self.to, &mut params);
+        Message::add_user_variables(&self.user_variables, &mut params);
+        self.options.add_params(&mut params);
+
+        let mime = reqwest::multipart::Part::bytes(self.mime.clone())
+            .file_name("message.mime")
+            .mime_str("message/rfc822")?;
+        Ok(params.into_form().part("message", mime))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct EmailAddress {
     name: Option<String>,
     address: String,
@@ -112,13 +416,330 @@ impl EmailAddress {
             address: address.to_string(),
         }
     }
+
+    /// The bare address, without the display name.
+    pub fn email(&self) -> &str {
+        &self.address
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Returned when a string cannot be parsed as an [`EmailAddress`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressParseError(String);
+
+impl fmt::Display for AddressParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid email address: {}", self.0)
+    }
+}
+
+impl std::error::Error for AddressParseError {}
+
+/// Parses `address@example.com`, `Name <address@example.com>` or
+/// `"Last, First" <address@example.com>`.
+impl FromStr for EmailAddress {
+    type Err = AddressParseError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || AddressParseError(value.to_string());
+        let value = value.trim();
+        let (name, address) = match value.strip_suffix('>').and_then(|v| v.rsplit_once('<')) {
+            Some((name, address)) => (name.trim(), address.trim()),
+            None => ("", value),
+        };
+
+        let valid = match address.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty()
+                    && !domain.is_empty()
+                    && !domain.contains('@')
+                    && !address.contains(|c: char| c.is_whitespace() || "<>,;".contains(c))
+            }
+            None => false,
+        };
+        if !valid {
+            return Err(invalid());
+        }
+
+        let name = match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+            Some(quoted) => {
+                let mut unquoted = String::with_capacity(quoted.len());
+                let mut chars = quoted.chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => unquoted.extend(chars.next()),
+                        c => unquoted.push(c),
+                    }
+                }
+                unquoted
+            }
+            None => name.to_string(),
+        };
+        Ok(EmailAddress {
+            name: if name.is_empty() { None } else { Some(name) },
+            address: address.to_string(),
+        })
+    }
 }
 
 impl fmt::Display for EmailAddress {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.name {
+            // Quote names that would otherwise split the address or end it early.
+            Some(ref name) if name.contains(|c: char| ",;:<>\"\\".contains(c)) => {
+                let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+                write!(f, "\"{}\" <{}>", escaped, self.address)
+            }
             Some(ref name) => write!(f, "{} <{}>", name, self.address),
             None => write!(f, "{}", self.address),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message_params(message: &Message) -> Params {
+        let mut params = Params::new();
+        message.add_params(&mut params);
+        params
+    }
+
+    #[test]
+    fn sends_recipients_and_repeated_fields_in_order() {
+        let message = Message::builder()
+            .to(EmailAddress::address("alice@example.com"))
+            .cc(EmailAddress::name_address("Smith, Bob", "bob@example.com"))
+            .cc(EmailAddress::address("carol@example.com"))
+            .subject("Hello")
+            .text("Hi")
+            .tag("first")
+            .tag("second")
+            .build()
+            .unwrap();
+        let params = message_params(&message);
+
+        assert_eq!(
+            params.get_all("cc"),
+            vec![r#""Smith, Bob" <bob@example.com>,carol@example.com"#]
+        );
+        assert_eq!(params.get_all("o:tag"), vec!["first", "second"]);
+        assert!(params.get_all("bcc").is_empty());
+    }
+
+    #[test]
+    fn sends_headers_as_repeated_h_fields() {
+        let message = Message::builder()
+            .to(EmailAddress::address("alice@example.com"))
+            .subject("Hello")
+            .text("Hi")
+            .header("X-Campaign", "spring")
+            .header("Reply-To", "support@example.com")
+            .header("X-Campaign", "summer")
+            .build()
+            .unwrap();
+        let params = message_params(&message);
+
+        let headers: Vec<(&str, &str)> = params
+            .fields()
+            .into_iter()
+            .filter(|(key, _)| key.starts_with("h:"))
+            .collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("h:X-Campaign", "spring"),
+                ("h:Reply-To", "support@example.com"),
+                ("h:X-Campaign", "summer"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_string_user_variables_raw_and_others_as_json() {
+        let message = Message::builder()
+            .to(EmailAddress::address("alice@example.com"))
+            .subject("Hello")
+            .text("Hi")
+            .user_variable("order", json!("A-42"))
+            .user_variable("quoted", json!("\"already quoted\""))
+            .user_variable("count", json!(3))
+            .user_variable("paid", json!(true))
+            .user_variable("customer", json!({ "id": 7, "tier": "gold" }))
+            .build()
+            .unwrap();
+        let params = message_params(&message);
+
+        assert_eq!(params.get_all("v:order"), vec!["A-42"]);
+        assert_eq!(params.get_all("v:quoted"), vec!["\"already quoted\""]);
+        assert_eq!(params.get_all("v:count"), vec!["3"]);
+        assert_eq!(params.get_all("v:paid"), vec!["true"]);
+        let customer: Value = serde_json::from_str(params.get_all("v:customer")[0]).unwrap();
+        assert_eq!(customer, json!({ "id": 7, "tier": "gold" }));
+    }
+
+    #[test]
+    fn sends_template_options_only_with_a_template() {
+        let message = Message::builder()
+            .to(EmailAddress::address("alice@example.com"))
+            .template("welcome")
+            .template_version("v2")
+            .template_text(true)
+            .template_var("name", "Alice")
+            .build()
+            .unwrap();
+        let params = message_params(&message);
+        assert_eq!(params.get_all("template"), vec!["welcome"]);
+        assert_eq!(params.get_all("t:version"), vec!["v2"]);
+        assert_eq!(params.get_all("t:text"), vec!["yes"]);
+        let vars: Value = serde_json::from_str(params.get_all("t:variables")[0]).unwrap();
+        assert_eq!(vars, json!({ "name": "Alice" }));
+
+        // Left over from an earlier template, but meaningless without one.
+        let message = Message {
+            to: vec![EmailAddress::address("alice@example.com")],
+            subject: Some(String::from("Hello")),
+            text: Some(String::from("Hi")),
+            template_vars: Some(json!({ "name": "Alice" })),
+            template_version: Some(String::from("v2")),
+            template_text: true,
+            ..Default::default()
+        };
+        let params = message_params(&message);
+        assert!(params
+            .fields()
+            .iter()
+            .all(|(key, _)| *key != "template" && !key.starts_with("t:")));
+    }
+
+    #[test]
+    fn splits_batches_into_thousands_with_their_variables() {
+        let recipients: Vec<(EmailAddress, Value)> = (0..2500)
+            .map(|i| {
+                let address = EmailAddress::name_address(
+                    &format!("User {}", i),
+                    &format!("user{}@example.com", i),
+                );
+                (address, json!({ "index": i }))
+            })
+            .collect();
+        let requests = batch_requests(&recipients);
+
+        let sizes: Vec<usize> = requests.iter().map(|(to, _)| to.len()).collect();
+        assert_eq!(sizes, vec![1000, 1000, 500]);
+
+        for (chunk, (to, params)) in requests.iter().enumerate() {
+            let offset = chunk * BATCH_LIMIT;
+            assert_eq!(to[0], recipients[offset].0);
+            assert_eq!(to[to.len() - 1], recipients[offset + to.len() - 1].0);
+
+            let fields = params.get_all("recipient-variables");
+            assert_eq!(fields.len(), 1);
+            let variables: serde_json::Map<String, Value> =
+                serde_json::from_str(fields[0]).unwrap();
+            assert_eq!(variables.len(), to.len());
+            // Keyed by the bare address, without the display name.
+            assert_eq!(
+                variables[&format!("user{}@example.com", offset)],
+                json!({ "index": offset })
+            );
+        }
+    }
+
+    #[test]
+    fn sends_a_small_batch_in_one_request() {
+        let recipients = vec![
+            (
+                EmailAddress::address("alice@example.com"),
+                json!({ "first": "Alice" }),
+            ),
+            (
+                EmailAddress::address("bob@example.com"),
+                json!({ "first": "Bob" }),
+            ),
+        ];
+        let requests = batch_requests(&recipients);
+        assert_eq!(requests.len(), 1);
+        let variables: Value =
+            serde_json::from_str(requests[0].1.get_all("recipient-variables")[0]).unwrap();
+        assert_eq!(
+            variables,
+            json!({
+                "alice@example.com": { "first": "Alice" },
+                "bob@example.com": { "first": "Bob" }
+            })
+        );
+    }
+
+    #[test]
+    fn parses_addresses() {
+        let parse = |value: &str| value.parse::<EmailAddress>();
+        assert_eq!(
+            parse(" bob@example.com "),
+            Ok(EmailAddress::address("bob@example.com"))
+        );
+        assert_eq!(
+            parse("Bob Smith <bob@example.com>"),
+            Ok(EmailAddress::name_address("Bob Smith", "bob@example.com"))
+        );
+        assert_eq!(
+            parse("<bob@example.com>"),
+            Ok(EmailAddress::address("bob@example.com"))
+        );
+        assert_eq!(
+            parse(r#""Smith, Bob \"B\" \\" <bob@example.com>"#),
+            Ok(EmailAddress::name_address(
+                r#"Smith, Bob "B" \"#,
+                "bob@example.com"
+            ))
+        );
+        for invalid in &[
+            "",
+            "<>",
+            "bob",
+            "@example.com",
+            "bob@",
+            "bob@a@example.com",
+            "a@x.com, b@y.com",
+            "a@x.com,b@y.com",
+            "undisclosed-recipients:;",
+            "Bob <bob smith@example.com>",
+        ] {
+            assert!(parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn exposes_address_parts() {
+        let named = EmailAddress::name_address("Bob", "bob@example.com");
+        assert_eq!(named.email(), "bob@example.com");
+        assert_eq!(named.name(), Some("Bob"));
+        let bare = EmailAddress::address("bob@example.com");
+        assert_eq!(bare.email(), "bob@example.com");
+        assert_eq!(bare.name(), None);
+    }
+
+    #[test]
+    fn quotes_names_when_displayed() {
+        let plain = EmailAddress::name_address("Bob Smith", "bob@example.com");
+        assert_eq!(plain.to_string(), "Bob Smith <bob@example.com>");
+        let comma = EmailAddress::name_address("Smith, Bob", "bob@example.com");
+        assert_eq!(comma.to_string(), r#""Smith, Bob" <bob@example.com>"#);
+        let quotes = EmailAddress::name_address(r#"Bob "B" \"#, "bob@example.com");
+        assert_eq!(quotes.to_string(), r#""Bob \"B\" \\" <bob@example.com>"#);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for name in &["Bob", "Smith, Bob", "a:b;c", "<Bob>", r#"say "hi" \o/"#] {
+            let address = EmailAddress::name_address(name, "bob@example.com");
+            assert_eq!(address.to_string().parse(), Ok(address.clone()));
+        }
+    }
+}
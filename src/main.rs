@@ -1,32 +1,28 @@
-use mailgun_rs::{EmailAddress, Mailgun, Message};
-use std::collections::HashMap;
+use mailgun_rs::blocking::Mailgun;
+use mailgun_rs::{EmailAddress, Message};
 
 fn main() {
     let domain = "mailgun.hackerth.com";
     let key = "key-xxxxxx";
     let recipient = "dongrify@gmail.com";
 
-    send_html(recipient, key, domain);
-    send_template(recipient, key, domain);
+    let client = Mailgun::new(key, domain).expect("failed to start the client");
+
+    send_html(&client, recipient);
+    send_template(&client, recipient);
 }
 
-fn send_html(recipient: &str, key: &str, domain: &str) {
-    let recipient = EmailAddress::address(recipient);
-    let message = Message {
-        to: vec![recipient],
-        subject: String::from("mailgun-rs"),
-        html: String::from("<h1>hello from mailgun</h1>"),
-        ..Default::default()
-    };
+fn send_html(client: &Mailgun, recipient: &str) {
+    let message = Message::builder()
+        .to(EmailAddress::address(recipient))
+        .subject("mailgun-rs")
+        .html("<h1>hello from mailgun</h1>")
+        .build()
+        .expect("invalid message");
 
-    let client = Mailgun {
-        api_key: String::from(key),
-        domain: String::from(domain),
-        message,
-    };
     let sender = EmailAddress::name_address("no-reply", "no-reply@hackerth.com");
 
-    match client.send(&sender) {
+    match client.send(&sender, &message) {
         Ok(_) => {
             println!("successful");
         }
@@ -36,27 +32,18 @@ fn send_html(recipient: &str, key: &str, domain: &str) {
     }
 }
 
-fn send_template(recipient: &str, key: &str, domain: &str) {
-    let mut template_vars = HashMap::new();
-    template_vars.insert(String::from("firstname"), String::from("Dongri"));
-
-    let recipient = EmailAddress::address(recipient);
-    let message = Message {
-        to: vec![recipient],
-        subject: String::from("mailgun-rs"),
-        template: String::from("template-1"),
-        template_vars,
-        ..Default::default()
-    };
+fn send_template(client: &Mailgun, recipient: &str) {
+    let message = Message::builder()
+        .to(EmailAddress::address(recipient))
+        .subject("mailgun-rs")
+        .template("template-1")
+        .template_var("firstname", "Dongri")
+        .build()
+        .expect("invalid message");
 
-    let client = Mailgun {
-        api_key: String::from(key),
-        domain: String::from(domain),
-        message,
-    };
     let sender = EmailAddress::name_address("no-reply", "no-reply@hackerth.com");
 
-    match client.send(&sender) {
+    match client.send(&sender, &message) {
         Ok(_) => {
             println!("successful");
         }
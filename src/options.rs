@@ -0,0 +1,235 @@
+use crate::params::Params;
+use crate::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+/// How far ahead Mailgun accepts scheduled messages.
+const MAX_SCHEDULE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// Delivery options sent as `o:` fields. Unset options fall back to the
+/// domain's settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SendOptions {
+    /// Tags for the message, one `o:tag` field each.
+    pub tags: Vec<String>,
+    pub tracking: Option<bool>,
+    pub tracking_clicks: Option<TrackingClicks>,
+    pub tracking_opens: Option<bool>,
+    pub dkim: Option<bool>,
+    /// Only deliver over TLS.
+    pub require_tls: Option<bool>,
+    /// Skip certificate and hostname verification when delivering over TLS.
+    pub skip_verification: Option<bool>,
+    /// Accept the message without delivering it.
+    pub test_mode: bool,
+    pub sending_ip: Option<IpAddr>,
+    pub sending_ip_pool: Option<String>,
+    /// When to deliver the message, at most three days ahead. Anything that
+    /// converts into a `SystemTime`, such as a chrono `DateTime`, can be used.
+    pub delivery_time: Option<SystemTime>,
+    /// Let Mailgun pick the best delivery time within this period (24 to 72 hours).
+    pub delivery_time_optimize_period: Option<Duration>,
+    /// Deliver at this local time in each recipient's time zone, e.g. `"09:00"` or `"9:00AM"`.
+    pub time_zone_localize: Option<String>,
+}
+
+/// Values for `o:tracking-clicks`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackingClicks {
+    Yes,
+    No,
+    HtmlOnly,
+}
+
+impl fmt::Display for TrackingClicks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrackingClicks::Yes => write!(f, "yes"),
+            TrackingClicks::No => write!(f, "no"),
+            TrackingClicks::HtmlOnly => write!(f, "htmlonly"),
+        }
+    }
+}
+
+impl SendOptions {
+    pub(crate) fn add_params(&self, params: &mut Params) {
+        for tag in &self.tags {
+            params.push("o:tag", tag.as_str());
+        }
+        if let Some(tracking) = self.tracking {
+            params.push("o:tracking", yes_no(tracking));
+        }
+        if let Some(tracking_clicks) = self.tracking_clicks {
+            params.push("o:tracking-clicks", tracking_clicks.to_string());
+        }
+        if let Some(tracking_opens) = self.tracking_opens {
+            params.push("o:tracking-opens", yes_no(tracking_opens));
+        }
+        if let Some(dkim) = self.dkim {
+            params.push("o:dkim", yes_no(dkim));
+        }
+        if let Some(require_tls) = self.require_tls {
+            params.push("o:require-tls", yes_no(require_tls));
+        }
+        if let Some(skip_verification) = self.skip_verification {
+            params.push("o:skip-verification", yes_no(skip_verification));
+        }
+        if self.test_mode {
+            params.push("o:testmode", "yes");
+        }
+        if let Some(sending_ip) = self.sending_ip {
+            params.push("o:sending-ip", sending_ip.to_string());
+        }
+        if let Some(ref sending_ip_pool) = self.sending_ip_pool {
+            params.push("o:sending-ip-pool", sending_ip_pool.as_str());
+        }
+        if let Some(delivery_time) = self.delivery_time {
+            params.push("o:deliverytime", httpdate::fmt_http_date(delivery_time));
+        }
+        if let Some(period) = self.delivery_time_optimize_period {
+            params.push(
+                "o:deliverytime-optimize-period",
+                format!("{}h", period.as_secs() / 3600),
+            );
+        }
+        if let Some(ref time_zone_localize) = self.time_zone_localize {
+            params.push("o:time-zone-localize", time_zone_localize.as_str());
+        }
+    }
+
+    /// Rejects schedules Mailgun would refuse, before anything is sent.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if let Some(delivery_time) = self.delivery_time {
+            if delivery_time > SystemTime::now() + MAX_SCHEDULE {
+                return Err(Error::InvalidMessage(String::from(
+                    "delivery_time can be at most 3 days in the future",
+                )));
+            }
+        }
+        if let Some(period) = self.delivery_time_optimize_period {
+            let hours = period.as_secs() / 3600;
+            if period.as_secs() % 3600 != 0 || !(24..=72).contains(&hours) {
+                return Err(Error::InvalidMessage(String::from(
+                    "delivery_time_optimize_period must be a whole number of hours between 24 and 72",
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(options: &SendOptions) -> Params {
+        let mut params = Params::new();
+        options.add_params(&mut params);
+        params
+    }
+
+    #[test]
+    fn sends_nothing_by_default() {
+        assert!(params(&SendOptions::default()).fields().is_empty());
+    }
+
+    #[test]
+    fn renders_flags_as_yes_and_no() {
+        let options = SendOptions {
+            tags: vec![String::from("welcome"), String::from("onboarding")],
+            tracking: Some(true),
+            tracking_clicks: Some(TrackingClicks::HtmlOnly),
+            tracking_opens: Some(false),
+            dkim: Some(true),
+            require_tls: Some(true),
+            skip_verification: Some(false),
+            test_mode: true,
+            sending_ip: Some("192.0.2.1".parse().unwrap()),
+            sending_ip_pool: Some(String::from("pool-1")),
+            ..Default::default()
+        };
+        assert_eq!(
+            params(&options).fields(),
+            vec![
+                ("o:tag", "welcome"),
+                ("o:tag", "onboarding"),
+                ("o:tracking", "yes"),
+                ("o:tracking-clicks", "htmlonly"),
+                ("o:tracking-opens", "no"),
+                ("o:dkim", "yes"),
+                ("o:require-tls", "yes"),
+                ("o:skip-verification", "no"),
+                ("o:testmode", "yes"),
+                ("o:sending-ip", "192.0.2.1"),
+                ("o:sending-ip-pool", "pool-1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_click_tracking() {
+        assert_eq!(TrackingClicks::Yes.to_string(), "yes");
+        assert_eq!(TrackingClicks::No.to_string(), "no");
+        assert_eq!(TrackingClicks::HtmlOnly.to_string(), "htmlonly");
+    }
+
+    #[test]
+    fn sends_delivery_time_as_an_rfc_2822_date() {
+        let options = SendOptions {
+            delivery_time: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777)),
+            delivery_time_optimize_period: Some(Duration::from_secs(48 * 3600)),
+            time_zone_localize: Some(String::from("09:00")),
+            ..Default::default()
+        };
+        assert_eq!(
+            params(&options).fields(),
+            vec![
+                ("o:deliverytime", "Sun, 06 Nov 1994 08:49:37 GMT"),
+                ("o:deliverytime-optimize-period", "48h"),
+                ("o:time-zone-localize", "09:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_delivery_time_to_three_days_ahead() {
+        let at = |delivery_time| SendOptions {
+            delivery_time: Some(delivery_time),
+            ..Default::default()
+        };
+        let hour = Duration::from_secs(3600);
+        assert!(at(SystemTime::now() + 71 * hour).validate().is_ok());
+        assert!(at(SystemTime::now() - hour).validate().is_ok());
+        assert!(matches!(
+            at(SystemTime::now() + 73 * hour).validate(),
+            Err(Error::InvalidMessage(_))
+        ));
+    }
+
+    #[test]
+    fn requires_whole_hours_between_24_and_72_for_optimization() {
+        let period = |seconds| SendOptions {
+            delivery_time_optimize_period: Some(Duration::from_secs(seconds)),
+            ..Default::default()
+        };
+        for hours in &[24, 48, 72] {
+            assert!(period(hours * 3600).validate().is_ok(), "{}h", hours);
+        }
+        for seconds in &[0, 23 * 3600, 73 * 3600, 24 * 3600 + 1, 36 * 3600 + 1800] {
+            assert!(
+                matches!(period(*seconds).validate(), Err(Error::InvalidMessage(_))),
+                "{}s",
+                seconds
+            );
+        }
+    }
+}
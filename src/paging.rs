@@ -0,0 +1,44 @@
+use crate::{Mailgun, SendResult};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// One page of a list, with links to the neighbouring pages.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    #[serde(default)]
+    pub paging: Paging,
+}
+
+/// Absolute URLs of the neighbouring pages, as returned by Mailgun.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Paging {
+    pub first: Option<String>,
+    pub last: Option<String>,
+    pub next: Option<String>,
+    pub previous: Option<String>,
+}
+
+impl Mailgun {
+    /// Fetches the page after `page`, or `None` once the list is exhausted.
+    pub async fn next_page<T: DeserializeOwned>(
+        &self,
+        page: &Page<T>,
+    ) -> SendResult<Option<Page<T>>> {
+        match page.paging.next {
+            Some(ref next) if !page.items.is_empty() => {
+                let next = self.execute(self.request_url(Method::GET, next)).await?;
+                Ok(Some(next))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn next_page<T: DeserializeOwned>(&self, page: &Page<T>) -> SendResult<Option<Page<T>>> {
+        self.runtime.block_on(self.inner.next_page(page))
+    }
+}
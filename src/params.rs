@@ -0,0 +1,86 @@
+use reqwest::multipart::Form;
+use serde::Serialize;
+
+/// Ordered form fields that may repeat the same key, as Mailgun expects for
+/// `o:tag`, `h:` headers and similar parameters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub(crate) struct Params {
+    fields: Vec<(String, String)>,
+}
+
+impl Params {
+    pub(crate) fn new() -> Self {
+        Params::default()
+    }
+
+    pub(crate) fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.fields.push((key.into(), value.into()));
+    }
+
+    pub(crate) fn push_opt(&mut self, key: &str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.push(key, value.to_string());
+        }
+    }
+
+    /// The fields in the order they were pushed.
+    #[cfg(test)]
+    pub(crate) fn fields(&self) -> Vec<(&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// The values pushed under `key`, in order.
+    #[cfg(test)]
+    pub(crate) fn get_all(&self, key: &str) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub(crate) fn into_form(self) -> Form {
+        self.fields
+            .into_iter()
+            .fold(Form::new(), |form, (key, value)| form.text(key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_repeated_keys_in_order() {
+        let mut params = Params::new();
+        params.push("o:tag", "first");
+        params.push("subject", "Hello");
+        params.push("o:tag", "second");
+        params.push_opt("o:dkim", None::<&str>);
+        params.push_opt("o:testmode", Some("yes"));
+
+        assert_eq!(
+            params.fields(),
+            vec![
+                ("o:tag", "first"),
+                ("subject", "Hello"),
+                ("o:tag", "second"),
+                ("o:testmode", "yes"),
+            ]
+        );
+        assert_eq!(params.get_all("o:tag"), vec!["first", "second"]);
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::json!([
+                ["o:tag", "first"],
+                ["subject", "Hello"],
+                ["o:tag", "second"],
+                ["o:testmode", "yes"]
+            ])
+        );
+    }
+}
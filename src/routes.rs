@@ -0,0 +1,252 @@
+//! The Routes API: rules that forward or store incoming mail.
+
+use crate::params::Params;
+use crate::{EmailAddress, Mailgun, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::fmt;
+
+/// Which incoming messages a route applies to. Values are matched exactly;
+/// they are escaped before being written into the route expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Messages sent to this address.
+    MatchRecipient(EmailAddress),
+    /// Messages sent to any address at this domain.
+    MatchRecipientDomain(String),
+    /// Messages whose `name` header is `value`.
+    MatchHeader { name: String, value: String },
+    /// Every message. Usually given the lowest priority.
+    CatchAll,
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::MatchRecipient(recipient) => write!(
+                f,
+                "match_recipient(\"^{}$\")",
+                escape_regex(&recipient.address)
+            ),
+            Filter::MatchRecipientDomain(domain) => {
+                write!(f, "match_recipient(\".*@{}$\")", escape_regex(domain))
+            }
+            Filter::MatchHeader { name, value } => write!(
+                f,
+                "match_header(\"{}\", \"^{}$\")",
+                escape_quotes(name),
+                escape_regex(value)
+            ),
+            Filter::CatchAll => write!(f, "catch_all()"),
+        }
+    }
+}
+
+/// What a route does with a matching message. Actions run in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Forwards to an email address or posts to a URL.
+    Forward(String),
+    /// Stores the message for three days, optionally posting a notification
+    /// to `notify` with a link to retrieve it.
+    Store { notify: Option<String> },
+    /// Stops lower priority routes from running.
+    Stop,
+}
+
+impl Action {
+    pub fn forward_to(address: &EmailAddress) -> Self {
+        Action::Forward(address.address.clone())
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Forward(destination) => {
+                write!(f, "forward(\"{}\")", escape_quotes(destination))
+            }
+            Action::Store { notify: Some(url) } => {
+                write!(f, "store(notify=\"{}\")", escape_quotes(url))
+            }
+            Action::Store { notify: None } => write!(f, "store()"),
+            Action::Stop => write!(f, "stop()"),
+        }
+    }
+}
+
+/// A route as stored by Mailgun. `expression` and `actions` are returned in
+/// Mailgun's own syntax.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Route {
+    pub id: String,
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub expression: String,
+    #[serde(default)]
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct RouteList {
+    pub total_count: usize,
+    pub items: Vec<Route>,
+}
+
+/// A route to create or replace. Lower priorities run first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewRoute {
+    pub priority: u32,
+    pub description: Option<String>,
+    pub filter: Filter,
+    pub actions: Vec<Action>,
+}
+
+impl NewRoute {
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        params.push("priority", self.priority.to_string());
+        params.push_opt("description", self.description.as_ref());
+        params.push("expression", self.filter.to_string());
+        for action in &self.actions {
+            params.push("action", action.to_string());
+        }
+        params
+    }
+}
+
+#[derive(Deserialize)]
+struct RouteResponse {
+    route: Route,
+}
+
+fn escape_quotes(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\.+*?()|[]{}^$\"".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Mailgun {
+    pub async fn create_route(&self, route: &NewRoute) -> SendResult<Route> {
+        let request = self
+            .request(Method::POST, "v3/routes")
+            .multipart(route.params().into_form());
+        let response: RouteResponse = self.execute(request).await?;
+        Ok(response.route)
+    }
+
+    pub async fn list_routes(
+        &self,
+        limit: Option<usize>,
+        skip: Option<usize>,
+    ) -> SendResult<RouteList> {
+        let mut params = Params::new();
+        params.push_opt("limit", limit);
+        params.push_opt("skip", skip);
+        self.execute(self.request(Method::GET, "v3/routes").query(&params))
+            .await
+    }
+
+    pub async fn get_route(&self, id: &str) -> SendResult<Route> {
+        let path = format!("v3/routes/{}", id);
+        let response: RouteResponse = self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.route)
+    }
+
+    /// Replaces every field of an existing route.
+    pub async fn update_route(&self, id: &str, route: &NewRoute) -> SendResult<Route> {
+        let path = format!("v3/routes/{}", id);
+        let request = self
+            .request(Method::PUT, &path)
+            .multipart(route.params().into_form());
+        self.execute(request).await
+    }
+
+    pub async fn delete_route(&self, id: &str) -> SendResult<()> {
+        let path = format!("v3/routes/{}", id);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn create_route(&self, route: &NewRoute) -> SendResult<Route> {
+        self.runtime.block_on(self.inner.create_route(route))
+    }
+
+    pub fn list_routes(&self, limit: Option<usize>, skip: Option<usize>) -> SendResult<RouteList> {
+        self.runtime.block_on(self.inner.list_routes(limit, skip))
+    }
+
+    pub fn get_route(&self, id: &str) -> SendResult<Route> {
+        self.runtime.block_on(self.inner.get_route(id))
+    }
+
+    pub fn update_route(&self, id: &str, route: &NewRoute) -> SendResult<Route> {
+        self.runtime.block_on(self.inner.update_route(id, route))
+    }
+
+    pub fn delete_route(&self, id: &str) -> SendResult<()> {
+        self.runtime.block_on(self.inner.delete_route(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_filters() {
+        let recipient =
+            Filter::MatchRecipient(EmailAddress::name_address("Support", "help+a@example.com"));
+        assert_eq!(
+            recipient.to_string(),
+            r#"match_recipient("^help\+a@example\.com$")"#
+        );
+        let domain = Filter::MatchRecipientDomain(String::from("example.com"));
+        assert_eq!(domain.to_string(), r#"match_recipient(".*@example\.com$")"#);
+        let header = Filter::MatchHeader {
+            name: String::from("X-\"Tag\""),
+            value: String::from("a \"b\" (c)"),
+        };
+        assert_eq!(
+            header.to_string(),
+            r#"match_header("X-\"Tag\"", "^a \"b\" \(c\)$")"#
+        );
+        assert_eq!(Filter::CatchAll.to_string(), "catch_all()");
+    }
+
+    #[test]
+    fn escapes_actions() {
+        let forward = Action::Forward(String::from(r#"https://example.com/a\"b\"#));
+        assert_eq!(
+            forward.to_string(),
+            r#"forward("https://example.com/a\\\"b\\")"#
+        );
+        let store = Action::Store {
+            notify: Some(String::from(r"https://example.com/\")),
+        };
+        assert_eq!(
+            store.to_string(),
+            r#"store(notify="https://example.com/\\")"#
+        );
+        assert_eq!(Action::Store { notify: None }.to_string(), "store()");
+        assert_eq!(Action::Stop.to_string(), "stop()");
+        let to = Action::forward_to(&EmailAddress::name_address("Bob", "bob@example.com"));
+        assert_eq!(to.to_string(), r#"forward("bob@example.com")"#);
+    }
+}
@@ -0,0 +1,460 @@
+//! The domain Templates API: stored templates and their versions.
+
+use crate::params::Params;
+use crate::{Mailgun, Page, Paging, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A stored template. `version` is set when a single version was requested,
+/// `versions` when listing versions.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub version: Option<TemplateVersion>,
+    #[serde(default)]
+    pub versions: Vec<TemplateVersion>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVersion {
+    pub tag: String,
+    /// The template content. Only returned when a single version is fetched.
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub engine: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// A template to create, optionally with its first version.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NewTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    /// The first version, which is always made active.
+    pub version: Option<NewTemplateVersion>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NewTemplateVersion {
+    pub tag: String,
+    pub template: String,
+    pub comment: Option<String>,
+    /// The template engine, `handlebars` unless set.
+    pub engine: Option<String>,
+    /// Headers stored with the version, such as `Subject` or `From`.
+    pub headers: HashMap<String, String>,
+    /// Make this the active version.
+    pub active: bool,
+}
+
+/// Changes to an existing version. Unset fields are left as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TemplateVersionUpdate {
+    pub template: Option<String>,
+    pub comment: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
+    pub active: bool,
+}
+
+/// One page of a template's versions.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TemplateVersions {
+    pub template: Template,
+    #[serde(default)]
+    pub paging: Paging,
+}
+
+#[derive(Deserialize)]
+struct TemplateResponse {
+    template: Template,
+}
+
+impl NewTemplateVersion {
+    fn add_params(&self, params: &mut Params) {
+        params.push("tag", self.tag.as_str());
+        params.push("template", self.template.as_str());
+        params.push_opt("comment", self.comment.as_ref());
+        params.push_opt("engine", self.engine.as_ref());
+        if !self.headers.is_empty() {
+            params.push("headers", headers_json(&self.headers));
+        }
+        if self.active {
+            params.push("active", "yes");
+        }
+    }
+}
+
+fn headers_json(headers: &HashMap<String, String>) -> String {
+    serde_json::json!(headers).to_string()
+}
+
+impl Mailgun {
+    fn templates_path(&self) -> String {
+        format!("v3/{}/templates", self.domain)
+    }
+
+    pub async fn create_template(&self, template: &NewTemplate) -> SendResult<Template> {
+        let mut params = Params::new();
+        params.push("name", template.name.as_str());
+        params.push_opt("description", template.description.as_ref());
+        if let Some(ref version) = template.version {
+            version.add_params(&mut params);
+        }
+
+        let request = self
+            .request(Method::POST, &self.templates_path())
+            .multipart(params.into_form());
+        let response: TemplateResponse = self.execute(request).await?;
+        Ok(response.template)
+    }
+
+    /// Fetches a template together with the content of its active version.
+    pub async fn get_template(&self, name: &str) -> SendResult<Template> {
+        let path = format!("{}/{}", self.templates_path(), name);
+        let request = self.request(Method::GET, &path).query(&[("active", "yes")]);
+        let response: TemplateResponse = self.execute(request).await?;
+        Ok(response.template)
+    }
+
+    pub async fn update_template(&self, name: &str, description: &str) -> SendResult<()> {
+        let path = format!("{}/{}", self.templates_path(), name);
+        let mut params = Params::new();
+        params.push("description", description);
+
+        self.execute_empty(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+
+    /// Deletes a template and all of its versions.
+    pub async fn delete_template(&self, name: &str) -> SendResult<()> {
+        let path = format!("{}/{}", self.templates_path(), name);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+
+    /// Lists the first page of templates. Use [`Mailgun::next_page`] for the rest.
+    pub async fn list_templates(&self, limit: Option<usize>) -> SendResult<Page<Template>> {
+        let mut params = Params::new();
+        params.push_opt("limit", limit);
+        let request = self
+            .request(Method::GET, &self.templates_path())
+            .query(&params);
+        self.execute(request).await
+    }
+
+    pub async fn create_template_version(
+        &self,
+        name: &str,
+        version: &NewTemplateVersion,
+    ) -> SendResult<Template> {
+        let path = format!("{}/{}/versions", self.templates_path(), name);
+        let mut params = Params::new();
+        version.add_params(&mut params);
+
+        let request = self
+            .request(Method::POST, &path)
+            .multipart(params.into_form());
+        let response: TemplateResponse = self.execute(request).await?;
+        Ok(response.template)
+    }
+
+    pub async fn get_template_version(&self, name: &str, tag: &str) -> SendResult<Template> {
+        let path = format!("{}/{}/versions/{}", self.templates_path(), name, tag);
+        let response: TemplateResponse = self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.template)
+    }
+
+    pub async fn update_template_version(
+        &self,
+        name: &str,
+        tag: &str,
+        update: &TemplateVersionUpdate,
+    ) -> SendResult<()> {
+        let path = format!("{}/{}/versions/{}", self.templates_path(), name, tag);
+        let mut params = Params::new();
+        params.push_opt("template", update.template.as_ref());
+        params.push_opt("comment", update.comment.as_ref());
+        if let Some(ref headers) = update.headers {
+            params.push("headers", headers_json(headers));
+        }
+        if update.active {
+            params.push("active", "yes");
+        }
+
+        self.execute_empty(
+            self.request(Method::PUT, &path)
+                .multipart(params.into_form()),
+        )
+        .await
+    }
+
+    /// Makes `tag` the version used when a message names this template.
+    pub async fn activate_template_version(&self, name: &str, tag: &str) -> SendResult<()> {
+        let update = TemplateVersionUpdate {
+            active: true,
+            ..Default::default()
+        };
+        self.update_template_version(name, tag, &update).await
+    }
+
+    pub async fn delete_template_version(&self, name: &str, tag: &str) -> SendResult<()> {
+        let path = format!("{}/{}/versions/{}", self.templates_path(), name, tag);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+
+    /// Lists the first page of a template's versions.
+    pub async fn list_template_versions(
+        &self,
+        name: &str,
+        limit: Option<usize>,
+    ) -> SendResult<TemplateVersions> {
+        let path = format!("{}/{}/versions", self.templates_path(), name);
+        let mut params = Params::new();
+        params.push_opt("limit", limit);
+        self.execute(self.request(Method::GET, &path).query(&params))
+            .await
+    }
+
+    /// Fetches the page of versions after `page`, or `None` once they are exhausted.
+    pub async fn next_template_versions(
+        &self,
+        page: &TemplateVersions,
+    ) -> SendResult<Option<TemplateVersions>> {
+        match page.paging.next {
+            Some(ref next) if !page.template.versions.is_empty() => {
+                let next = self.execute(self.request_url(Method::GET, next)).await?;
+                Ok(Some(next))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn create_template(&self, template: &NewTemplate) -> SendResult<Template> {
+        self.runtime.block_on(self.inner.create_template(template))
+    }
+
+    pub fn get_template(&self, name: &str) -> SendResult<Template> {
+        self.runtime.block_on(self.inner.get_template(name))
+    }
+
+    pub fn update_template(&self, name: &str, description: &str) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_template(name, description))
+    }
+
+    pub fn delete_template(&self, name: &str) -> SendResult<()> {
+        self.runtime.block_on(self.inner.delete_template(name))
+    }
+
+    pub fn list_templates(&self, limit: Option<usize>) -> SendResult<Page<Template>> {
+        self.runtime.block_on(self.inner.list_templates(limit))
+    }
+
+    pub fn create_template_version(
+        &self,
+        name: &str,
+        version: &NewTemplateVersion,
+    ) -> SendResult<Template> {
+        self.runtime
+            .block_on(self.inner.create_template_version(name, version))
+    }
+
+    pub fn get_template_version(&self, name: &str, tag: &str) -> SendResult<Template> {
+        self.runtime
+            .block_on(self.inner.get_template_version(name, tag))
+    }
+
+    pub fn update_template_version(
+        &self,
+        name: &str,
+        tag: &str,
+        update: &TemplateVersionUpdate,
+    ) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_template_version(name, tag, update))
+    }
+
+    pub fn activate_template_version(&self, name: &str, tag: &str) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.activate_template_version(name, tag))
+    }
+
+    pub fn delete_template_version(&self, name: &str, tag: &str) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.delete_template_version(name, tag))
+    }
+
+    pub fn list_template_versions(
+        &self,
+        name: &str,
+        limit: Option<usize>,
+    ) -> SendResult<TemplateVersions> {
+        self.runtime
+            .block_on(self.inner.list_template_versions(name, limit))
+    }
+
+    pub fn next_template_versions(
+        &self,
+        page: &TemplateVersions,
+    ) -> SendResult<Option<TemplateVersions>> {
+        self.runtime
+            .block_on(self.inner.next_template_versions(page))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_template_with_its_active_version() {
+        let body = r#"{
+            "template": {
+                "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                "createdBy": "user@example.com",
+                "description": "This is the description of the template",
+                "name": "template.name",
+                "id": "48d63154-8c8f-4104-ab14-687d01dbf296",
+                "version": {
+                    "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                    "engine": "handlebars",
+                    "tag": "v0",
+                    "comment": "version comment",
+                    "template": "{{fname}} {{lname}}",
+                    "active": true,
+                    "headers": { "Subject": "Hello {{fname}}" }
+                }
+            }
+        }"#;
+        let template = serde_json::from_str::<TemplateResponse>(body)
+            .unwrap()
+            .template;
+        assert_eq!(template.name, "template.name");
+        assert_eq!(template.created_by.as_deref(), Some("user@example.com"));
+        assert!(template.versions.is_empty());
+
+        let version = template.version.unwrap();
+        assert_eq!(version.tag, "v0");
+        assert!(version.active);
+        assert_eq!(version.template.as_deref(), Some("{{fname}} {{lname}}"));
+        assert_eq!(version.engine.as_deref(), Some("handlebars"));
+        assert_eq!(version.headers["Subject"], "Hello {{fname}}");
+    }
+
+    #[test]
+    fn parses_a_page_of_versions() {
+        let body = r#"{
+            "template": {
+                "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                "description": "This is the description of the template",
+                "name": "template.name",
+                "versions": [
+                    {
+                        "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                        "engine": "handlebars",
+                        "tag": "v0",
+                        "comment": "version comment",
+                        "active": true
+                    },
+                    {
+                        "createdAt": "Wed, 30 Aug 2018 23:31:11 UTC",
+                        "engine": "handlebars",
+                        "tag": "v1",
+                        "comment": "version comment"
+                    }
+                ]
+            },
+            "paging": {
+                "first": "https://api.mailgun.net/v3/example.com/templates/template.name/versions?limit=10",
+                "last": "https://api.mailgun.net/v3/example.com/templates/template.name/versions?page=last&limit=10",
+                "next": "https://api.mailgun.net/v3/example.com/templates/template.name/versions?page=next&p=v1&limit=10",
+                "previous": "https://api.mailgun.net/v3/example.com/templates/template.name/versions?page=previous&p=v0&limit=10"
+            }
+        }"#;
+        let page: TemplateVersions = serde_json::from_str(body).unwrap();
+        let tags: Vec<(&str, bool)> = page
+            .template
+            .versions
+            .iter()
+            .map(|version| (version.tag.as_str(), version.active))
+            .collect();
+        assert_eq!(tags, vec![("v0", true), ("v1", false)]);
+        // Listed versions come without their content.
+        assert!(page.template.versions[0].template.is_none());
+        assert!(page.paging.next.unwrap().contains("p=v1"));
+    }
+
+    #[test]
+    fn parses_a_page_of_templates() {
+        let body = r#"{
+            "items": [
+                {
+                    "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                    "description": "This is the description of the template",
+                    "name": "template.name"
+                }
+            ],
+            "paging": {
+                "first": "https://api.mailgun.net/v3/example.com/templates?limit=10",
+                "last": "https://api.mailgun.net/v3/example.com/templates?page=last&limit=10",
+                "next": "https://api.mailgun.net/v3/example.com/templates?page=next&p=template.name&limit=10",
+                "previous": "https://api.mailgun.net/v3/example.com/templates?page=previous&p=template.name&limit=10"
+            }
+        }"#;
+        let page: Page<Template> = serde_json::from_str(body).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert!(page.items[0].version.is_none());
+        assert!(page.paging.next.is_some());
+    }
+
+    #[test]
+    fn parses_the_create_response() {
+        let body = r#"{
+            "message": "template has been stored",
+            "template": {
+                "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                "description": "This is the description of the template",
+                "name": "template.name",
+                "version": {
+                    "createdAt": "Wed, 29 Aug 2018 23:31:11 UTC",
+                    "engine": "handlebars",
+                    "tag": "initial",
+                    "comment": "version comment"
+                }
+            }
+        }"#;
+        let template = serde_json::from_str::<TemplateResponse>(body)
+            .unwrap()
+            .template;
+        assert_eq!(template.version.unwrap().tag, "initial");
+    }
+}
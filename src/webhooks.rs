@@ -0,0 +1,792 @@
+//! Webhooks: registering them, checking that a request really came from
+//! Mailgun, and reading what it says.
+
+use crate::events::Event;
+use crate::params::Params;
+use crate::{Mailgun, SendResult};
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How old a webhook may be by default before it is rejected.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// The `signature` block Mailgun adds to every webhook.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub timestamp: String,
+    pub token: String,
+    pub signature: String,
+}
+
+/// The JSON body of a webhook request.
+///
+/// `event_data` uses the same types as the Events API. The `permanent_fail`
+/// and `temporary_fail` webhooks both carry an [`Event::Failed`], told apart
+/// by its `severity`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct WebhookPayload {
+    pub signature: Signature,
+    #[serde(rename = "event-data")]
+    pub event_data: Event,
+}
+
+/// Remembers tokens that have already been seen, to reject replayed webhooks.
+pub trait TokenCache {
+    /// Records `token`, keeping it at least until `expires_at`. Returns
+    /// `false` if the token was already recorded.
+    fn insert(&self, token: &str, expires_at: SystemTime) -> bool;
+}
+
+/// A [`TokenCache`] kept in process memory. Expired tokens are dropped as new
+/// ones are inserted.
+#[derive(Debug, Default)]
+pub struct MemoryTokenCache {
+    tokens: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl MemoryTokenCache {
+    pub fn new() -> Self {
+        MemoryTokenCache::default()
+    }
+}
+
+impl TokenCache for MemoryTokenCache {
+    fn insert(&self, token: &str, expires_at: SystemTime) -> bool {
+        let now = SystemTime::now();
+        let mut tokens = match self.tokens.lock() {
+            Ok(tokens) => tokens,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        tokens.retain(|_, expires_at| *expires_at > now);
+        tokens.insert(token.to_string(), expires_at).is_none()
+    }
+}
+
+/// Why a webhook signature was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The timestamp or signature is not in the expected format.
+    Malformed,
+    /// The timestamp is older than the allowed age, or in the future.
+    Expired,
+    /// The signature does not match the signing key.
+    InvalidSignature,
+    /// The token has been seen before.
+    Replayed,
+    /// The request has no signature at all.
+    Unsigned,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationError::Malformed => write!(f, "malformed webhook signature"),
+            VerificationError::Expired => write!(f, "webhook timestamp is too old"),
+            VerificationError::InvalidSignature => write!(f, "invalid webhook signature"),
+            VerificationError::Replayed => write!(f, "webhook token was already used"),
+            VerificationError::Unsigned => write!(f, "webhook is not signed"),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Verifies webhook signatures with the account's HTTP webhook signing key.
+pub struct WebhookVerifier {
+    signing_key: String,
+    max_age: Duration,
+    token_cache: Option<Box<dyn TokenCache + Send + Sync>>,
+}
+
+impl WebhookVerifier {
+    /// Creates a verifier that accepts webhooks up to five minutes old and
+    /// does not check for replays.
+    pub fn new(signing_key: &str) -> Self {
+        WebhookVerifier {
+            signing_key: signing_key.to_string(),
+            max_age: DEFAULT_MAX_AGE,
+            token_cache: None,
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Rejects tokens that `cache` has already seen.
+    pub fn with_token_cache(mut self, cache: impl TokenCache + Send + Sync + 'static) -> Self {
+        self.token_cache = Some(Box::new(cache));
+        self
+    }
+
+    /// Checks the timestamp, then the signature, then (if a cache is set) that
+    /// the token has not been used before.
+    pub fn verify(&self, signature: &Signature) -> Result<(), VerificationError> {
+        let now = SystemTime::now();
+        let seconds: u64 = signature
+            .timestamp
+            .parse()
+            .map_err(|_| VerificationError::Malformed)?;
+        let timestamp = UNIX_EPOCH
+            .checked_add(Duration::from_secs(seconds))
+            .ok_or(VerificationError::Malformed)?;
+        let age = match now.duration_since(timestamp) {
+            Ok(age) => age,
+            Err(err) => err.duration(),
+        };
+        if age > self.max_age {
+            return Err(VerificationError::Expired);
+        }
+
+        let expected = decode_hex(&signature.signature).ok_or(VerificationError::Malformed)?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.signing_key.as_bytes())
+            .map_err(|_| VerificationError::Malformed)?;
+        mac.update(signature.timestamp.as_bytes());
+        mac.update(signature.token.as_bytes());
+        // `verify_slice` compares in constant time.
+        mac.verify_slice(&expected)
+            .map_err(|_| VerificationError::InvalidSignature)?;
+
+        if let Some(ref cache) = self.token_cache {
+            let expires_at = timestamp.checked_add(self.max_age).unwrap_or(timestamp);
+            if !cache.insert(&signature.token, expires_at) {
+                return Err(VerificationError::Replayed);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for WebhookVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookVerifier")
+            .field("max_age", &self.max_age)
+            .field("token_cache", &self.token_cache.is_some())
+            .finish()
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match std::str::from_utf8(pair) {
+            // `from_str_radix` would also accept a sign, as in "+f".
+            Ok(pair) if pair.len() == 2 && pair.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                u8::from_str_radix(pair, 16).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The events a webhook can be registered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEvent {
+    Accepted,
+    Delivered,
+    PermanentFail,
+    TemporaryFail,
+    Opened,
+    Clicked,
+    Unsubscribed,
+    Complained,
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            WebhookEvent::Accepted => "accepted",
+            WebhookEvent::Delivered => "delivered",
+            WebhookEvent::PermanentFail => "permanent_fail",
+            WebhookEvent::TemporaryFail => "temporary_fail",
+            WebhookEvent::Opened => "opened",
+            WebhookEvent::Clicked => "clicked",
+            WebhookEvent::Unsubscribed => "unsubscribed",
+            WebhookEvent::Complained => "complained",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "accepted" => Ok(WebhookEvent::Accepted),
+            "delivered" => Ok(WebhookEvent::Delivered),
+            "permanent_fail" => Ok(WebhookEvent::PermanentFail),
+            "temporary_fail" => Ok(WebhookEvent::TemporaryFail),
+            "opened" => Ok(WebhookEvent::Opened),
+            "clicked" => Ok(WebhookEvent::Clicked),
+            "unsubscribed" => Ok(WebhookEvent::Unsubscribed),
+            "complained" => Ok(WebhookEvent::Complained),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A webhook registered for the whole account rather than one domain.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountWebhook {
+    pub webhook_id: String,
+    pub url: String,
+    /// Event types this crate does not know about are left out.
+    #[serde(default, deserialize_with = "known_events")]
+    pub event_types: Vec<WebhookEvent>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// An account-level webhook to create or replace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NewAccountWebhook {
+    pub url: String,
+    pub event_types: Vec<WebhookEvent>,
+    pub description: Option<String>,
+}
+
+impl NewAccountWebhook {
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        params.push("url", self.url.as_str());
+        for event in &self.event_types {
+            params.push("event_types", event.to_string());
+        }
+        params.push_opt("description", self.description.as_ref());
+        params
+    }
+}
+
+#[derive(Deserialize)]
+struct WebhookUrls {
+    #[serde(default)]
+    urls: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct DomainWebhooksResponse {
+    webhooks: HashMap<String, WebhookUrls>,
+}
+
+impl DomainWebhooksResponse {
+    /// Drops events this crate does not know about.
+    fn into_known(self) -> HashMap<WebhookEvent, Vec<String>> {
+        self.webhooks
+            .into_iter()
+            .filter_map(|(name, webhook)| Some((name.parse().ok()?, webhook.urls)))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct DomainWebhookResponse {
+    webhook: WebhookUrls,
+}
+
+#[derive(Deserialize)]
+struct AccountWebhooksResponse {
+    webhooks: Vec<AccountWebhook>,
+}
+
+#[derive(Deserialize)]
+struct AccountWebhookResponse {
+    webhook: AccountWebhook,
+}
+
+#[derive(Deserialize)]
+struct CreatedWebhookResponse {
+    webhook_id: String,
+}
+
+fn known_events<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<WebhookEvent>, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names.iter().filter_map(|name| name.parse().ok()).collect())
+}
+
+fn url_params(urls: &[&str]) -> Params {
+    let mut params = Params::new();
+    for url in urls {
+        params.push("url", *url);
+    }
+    params
+}
+
+impl Mailgun {
+    fn webhooks_path(&self) -> String {
+        format!("v3/domains/{}/webhooks", self.domain)
+    }
+
+    /// Lists the URLs registered for each event on this client's domain.
+    pub async fn list_webhooks(&self) -> SendResult<HashMap<WebhookEvent, Vec<String>>> {
+        let response: DomainWebhooksResponse = self
+            .execute(self.request(Method::GET, &self.webhooks_path()))
+            .await?;
+        Ok(response.into_known())
+    }
+
+    pub async fn get_webhook(&self, event: WebhookEvent) -> SendResult<Vec<String>> {
+        let path = format!("{}/{}", self.webhooks_path(), event);
+        let response: DomainWebhookResponse =
+            self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.webhook.urls)
+    }
+
+    /// Registers up to three URLs for `event` on this client's domain.
+    pub async fn create_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<Vec<String>> {
+        let mut params = url_params(urls);
+        params.push("id", event.to_string());
+        let request = self
+            .request(Method::POST, &self.webhooks_path())
+            .multipart(params.into_form());
+        let response: DomainWebhookResponse = self.execute(request).await?;
+        Ok(response.webhook.urls)
+    }
+
+    /// Replaces the URLs registered for `event`.
+    pub async fn update_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<Vec<String>> {
+        let path = format!("{}/{}", self.webhooks_path(), event);
+        let request = self
+            .request(Method::PUT, &path)
+            .multipart(url_params(urls).into_form());
+        let response: DomainWebhookResponse = self.execute(request).await?;
+        Ok(response.webhook.urls)
+    }
+
+    pub async fn delete_webhook(&self, event: WebhookEvent) -> SendResult<()> {
+        let path = format!("{}/{}", self.webhooks_path(), event);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+
+    pub async fn list_account_webhooks(&self) -> SendResult<Vec<AccountWebhook>> {
+        let response: AccountWebhooksResponse = self
+            .execute(self.request(Method::GET, "v1/webhooks"))
+            .await?;
+        Ok(response.webhooks)
+    }
+
+    pub async fn get_account_webhook(&self, webhook_id: &str) -> SendResult<AccountWebhook> {
+        let path = format!("v1/webhooks/{}", webhook_id);
+        let response: AccountWebhookResponse =
+            self.execute(self.request(Method::GET, &path)).await?;
+        Ok(response.webhook)
+    }
+
+    /// Registers an account-level webhook and returns its id.
+    pub async fn create_account_webhook(&self, webhook: &NewAccountWebhook) -> SendResult<String> {
+        let request = self
+            .request(Method::POST, "v1/webhooks")
+            .multipart(webhook.params().into_form());
+        let response: CreatedWebhookResponse = self.execute(request).await?;
+        Ok(response.webhook_id)
+    }
+
+    pub async fn update_account_webhook(
+        &self,
+        webhook_id: &str,
+        webhook: &NewAccountWebhook,
+    ) -> SendResult<()> {
+        let path = format!("v1/webhooks/{}", webhook_id);
+        let request = self
+            .request(Method::PUT, &path)
+            .multipart(webhook.params().into_form());
+        self.execute_empty(request).await
+    }
+
+    pub async fn delete_account_webhook(&self, webhook_id: &str) -> SendResult<()> {
+        let path = format!("v1/webhooks/{}", webhook_id);
+        self.execute_empty(self.request(Method::DELETE, &path))
+            .await
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl crate::blocking::Mailgun {
+    pub fn list_webhooks(&self) -> SendResult<HashMap<WebhookEvent, Vec<String>>> {
+        self.runtime.block_on(self.inner.list_webhooks())
+    }
+
+    pub fn get_webhook(&self, event: WebhookEvent) -> SendResult<Vec<String>> {
+        self.runtime.block_on(self.inner.get_webhook(event))
+    }
+
+    pub fn create_webhook(&self, event: WebhookEvent, urls: &[&str]) -> SendResult<Vec<String>> {
+        self.runtime
+            .block_on(self.inner.create_webhook(event, urls))
+    }
+
+    pub fn update_webhook(&self, event: WebhookEvent, urls: &[&str]) -> SendResult<Vec<String>> {
+        self.runtime
+            .block_on(self.inner.update_webhook(event, urls))
+    }
+
+    pub fn delete_webhook(&self, event: WebhookEvent) -> SendResult<()> {
+        self.runtime.block_on(self.inner.delete_webhook(event))
+    }
+
+    pub fn list_account_webhooks(&self) -> SendResult<Vec<AccountWebhook>> {
+        self.runtime.block_on(self.inner.list_account_webhooks())
+    }
+
+    pub fn get_account_webhook(&self, webhook_id: &str) -> SendResult<AccountWebhook> {
+        self.runtime
+            .block_on(self.inner.get_account_webhook(webhook_id))
+    }
+
+    pub fn create_account_webhook(&self, webhook: &NewAccountWebhook) -> SendResult<String> {
+        self.runtime
+            .block_on(self.inner.create_account_webhook(webhook))
+    }
+
+    pub fn update_account_webhook(
+        &self,
+        webhook_id: &str,
+        webhook: &NewAccountWebhook,
+    ) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.update_account_webhook(webhook_id, webhook))
+    }
+
+    pub fn delete_account_webhook(&self, webhook_id: &str) -> SendResult<()> {
+        self.runtime
+            .block_on(self.inner.delete_account_webhook(webhook_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KEY: &str = "key-signing";
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn sign(key: &str, timestamp: u64, token: &str) -> Signature {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(token.as_bytes());
+        let signature = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Signature {
+            timestamp: timestamp.to_string(),
+            token: token.to_string(),
+            signature,
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        let verifier = WebhookVerifier::new(KEY);
+        assert_eq!(verifier.verify(&sign(KEY, now(), "token")), Ok(()));
+    }
+
+    #[test]
+    fn accepts_uppercase_hex() {
+        let mut signature = sign(KEY, now(), "token");
+        signature.signature = signature.signature.to_uppercase();
+        assert_eq!(WebhookVerifier::new(KEY).verify(&signature), Ok(()));
+    }
+
+    #[test]
+    fn rejects_a_wrong_key() {
+        let verifier = WebhookVerifier::new(KEY);
+        assert_eq!(
+            verifier.verify(&sign("another-key", now(), "token")),
+            Err(VerificationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn rejects_a_tampered_token() {
+        let mut signature = sign(KEY, now(), "token");
+        signature.token = String::from("other");
+        assert_eq!(
+            WebhookVerifier::new(KEY).verify(&signature),
+            Err(VerificationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        let verifier = WebhookVerifier::new(KEY);
+        let valid = sign(KEY, now(), "token");
+        for bad in &[
+            &valid.signature[1..],
+            "abc",
+            "zz",
+            "+f",
+            "héllo",
+            &valid.signature.replace(&valid.signature[..2], "g0"),
+        ] {
+            let signature = Signature {
+                signature: bad.to_string(),
+                ..valid.clone()
+            };
+            assert_eq!(
+                verifier.verify(&signature),
+                Err(VerificationError::Malformed),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_a_short_signature() {
+        let mut signature = sign(KEY, now(), "token");
+        signature.signature.truncate(32);
+        assert_eq!(
+            WebhookVerifier::new(KEY).verify(&signature),
+            Err(VerificationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        let verifier = WebhookVerifier::new(KEY);
+        for timestamp in &["", "soon", "-1", "1.5", "18446744073709551615"] {
+            let signature = Signature {
+                timestamp: timestamp.to_string(),
+                ..sign(KEY, now(), "token")
+            };
+            assert_eq!(
+                verifier.verify(&signature),
+                Err(VerificationError::Malformed),
+                "{:?}",
+                timestamp
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_old_and_future_timestamps() {
+        let verifier = WebhookVerifier::new(KEY).with_max_age(Duration::from_secs(60));
+        assert_eq!(verifier.verify(&sign(KEY, now() - 30, "token")), Ok(()));
+        assert_eq!(
+            verifier.verify(&sign(KEY, now() - 120, "token")),
+            Err(VerificationError::Expired)
+        );
+        assert_eq!(
+            verifier.verify(&sign(KEY, now() + 120, "token")),
+            Err(VerificationError::Expired)
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_tokens() {
+        let verifier = WebhookVerifier::new(KEY).with_token_cache(MemoryTokenCache::new());
+        let signature = sign(KEY, now(), "token");
+        assert_eq!(verifier.verify(&signature), Ok(()));
+        assert_eq!(
+            verifier.verify(&signature),
+            Err(VerificationError::Replayed)
+        );
+        assert_eq!(verifier.verify(&sign(KEY, now(), "fresh")), Ok(()));
+    }
+
+    #[test]
+    fn does_not_cache_tokens_with_a_bad_signature() {
+        let verifier = WebhookVerifier::new(KEY).with_token_cache(MemoryTokenCache::new());
+        let mut forged = sign(KEY, now(), "token");
+        forged.signature = sign("another-key", now(), "token").signature;
+        assert_eq!(
+            verifier.verify(&forged),
+            Err(VerificationError::InvalidSignature)
+        );
+        assert_eq!(verifier.verify(&sign(KEY, now(), "token")), Ok(()));
+    }
+
+    #[test]
+    fn memory_cache_drops_expired_tokens() {
+        let cache = MemoryTokenCache::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let future = SystemTime::now() + Duration::from_secs(60);
+        assert!(cache.insert("old", past));
+        assert!(cache.insert("new", future));
+        assert!(cache.insert("old", future));
+        assert!(!cache.insert("new", future));
+    }
+
+    /// Mailgun's sample `permanent_fail` webhook, as posted.
+    const PERMANENT_FAIL: &str = r#"{
+        "signature": {
+            "timestamp": "1529006854",
+            "token": "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0",
+            "signature": "d2271d12299f6592d9d44cd9d250f0704e4674c30d79d07c47a66f95ce71cf55"
+        },
+        "event-data": {
+            "event": "failed",
+            "id": "G9Bn5sl1TC6nu79C8C0bwg",
+            "timestamp": 1521233195.375624,
+            "log-level": "error",
+            "severity": "permanent",
+            "reason": "suppress-bounce",
+            "recipient": "alice@example.com",
+            "recipient-domain": "example.com",
+            "tags": ["my_tag_1", "my_tag_2"],
+            "campaigns": [],
+            "user-variables": { "my_var_1": "Mailgun Variable #1", "my-var-2": "awesome" },
+            "flags": { "is-routed": false, "is-authenticated": true, "is-system-test": false, "is-test-mode": false },
+            "envelope": {
+                "sender": "bob@sandbox.mailgun.org",
+                "transport": "smtp",
+                "targets": "alice@example.com"
+            },
+            "message": {
+                "headers": {
+                    "to": "Alice <alice@example.com>",
+                    "message-id": "20130503192659.13651.20287@sandbox.mailgun.org",
+                    "from": "Bob <bob@sandbox.mailgun.org>",
+                    "subject": "Test permanent_fail webhook"
+                },
+                "attachments": [],
+                "size": 111
+            },
+            "storage": {
+                "url": "https://se.api.mailgun.net/v3/domains/sandbox.mailgun.org/messages/message_key",
+                "key": "message_key"
+            },
+            "delivery-status": {
+                "attempt-no": 1,
+                "message": "",
+                "code": 605,
+                "description": "Not delivering to previously bounced address",
+                "session-seconds": 0.0
+            }
+        }
+    }"#;
+
+    #[test]
+    fn parses_a_sample_webhook() {
+        let payload: WebhookPayload = serde_json::from_str(PERMANENT_FAIL).unwrap();
+        assert_eq!(
+            payload.signature.token,
+            "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0"
+        );
+        match payload.event_data {
+            Event::Failed(failure) => {
+                assert!(failure.is_permanent());
+                assert_eq!(failure.info.id, "G9Bn5sl1TC6nu79C8C0bwg");
+                assert_eq!(failure.reason.as_deref(), Some("suppress-bounce"));
+                assert_eq!(failure.delivery_status.unwrap().code, Some(605));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_domain_webhooks() {
+        let body = json!({
+            "webhooks": {
+                "clicked": { "urls": ["https://example.com/clicked"] },
+                "delivered": { "urls": ["https://example.com/a", "https://example.com/b"] },
+                "opened": { "urls": [] },
+                "bogus": { "urls": ["https://example.com/bogus"] }
+            }
+        });
+        let response: DomainWebhooksResponse = serde_json::from_value(body).unwrap();
+        let webhooks = response.into_known();
+        assert_eq!(webhooks.len(), 3);
+        assert_eq!(
+            webhooks[&WebhookEvent::Clicked],
+            vec!["https://example.com/clicked"]
+        );
+        assert_eq!(
+            webhooks[&WebhookEvent::Delivered],
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+        assert!(webhooks[&WebhookEvent::Opened].is_empty());
+
+        let body = json!({ "webhook": { "urls": ["https://example.com/clicked"] } });
+        let response: DomainWebhookResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(response.webhook.urls, vec!["https://example.com/clicked"]);
+    }
+
+    #[test]
+    fn parses_account_webhooks() {
+        let body = json!({
+            "webhooks": [{
+                "webhook_id": "01HZ4C5G8T0F3XKQH9V6M2R7NP",
+                "url": "https://example.com/hook",
+                "event_types": ["delivered", "bogus", "permanent_fail"],
+                "description": "Deliveries and bounces",
+                "created_at": "2024-05-30T17:45:12Z"
+            }]
+        });
+        let response: AccountWebhooksResponse = serde_json::from_value(body).unwrap();
+        let webhook = &response.webhooks[0];
+        assert_eq!(webhook.webhook_id, "01HZ4C5G8T0F3XKQH9V6M2R7NP");
+        assert_eq!(
+            webhook.event_types,
+            vec![WebhookEvent::Delivered, WebhookEvent::PermanentFail]
+        );
+        assert_eq!(
+            webhook.description.as_deref(),
+            Some("Deliveries and bounces")
+        );
+
+        let body = json!({
+            "webhook": { "webhook_id": "abc", "url": "https://example.com/hook" }
+        });
+        let response: AccountWebhookResponse = serde_json::from_value(body).unwrap();
+        assert!(response.webhook.event_types.is_empty());
+        assert_eq!(response.webhook.created_at, None);
+
+        let body = json!({ "webhook_id": "abc" });
+        let response: CreatedWebhookResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(response.webhook_id, "abc");
+    }
+
+    #[test]
+    fn webhook_event_names_round_trip() {
+        let events = [
+            WebhookEvent::Accepted,
+            WebhookEvent::Delivered,
+            WebhookEvent::PermanentFail,
+            WebhookEvent::TemporaryFail,
+            WebhookEvent::Opened,
+            WebhookEvent::Clicked,
+            WebhookEvent::Unsubscribed,
+            WebhookEvent::Complained,
+        ];
+        for event in events.iter() {
+            assert_eq!(event.to_string().parse(), Ok(*event));
+        }
+        assert_eq!("failed".parse::<WebhookEvent>(), Err(()));
+    }
+
+    #[test]
+    fn rejects_payloads_without_a_signature() {
+        let body = r#"{ "event-data": { "event": "delivered", "id": "x", "timestamp": 1.0 } }"#;
+        assert!(serde_json::from_str::<WebhookPayload>(body).is_err());
+    }
+}